@@ -0,0 +1,129 @@
+use crate::Weight;
+use num_traits::{CheckedAdd, CheckedSub, Zero};
+use std::cmp::Ordering;
+use std::fmt::Display;
+use std::ops::{Add, Sub};
+
+// c - k·δ, where δ is a symbolic positive infinitesimal.
+// over a dense domain (rationals, reals), v - u < c is the same as v - u <= c - δ,
+// so strict constraints can be solved exactly, and a solution can be made concrete by picking a small enough δ.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub struct Delta<W: Weight> {
+    pub c: W,
+    pub k: i64,
+}
+
+impl<W: Weight> Delta<W> {
+    pub fn new(c: W, k: i64) -> Self {
+        Delta { c, k }
+    }
+}
+
+impl<W: Weight> PartialOrd for Delta<W> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<W: Weight> Ord for Delta<W> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // δ is smaller than any positive difference in c, so c decides unless it is equal.
+        self.c.cmp(&other.c).then(other.k.cmp(&self.k))
+    }
+}
+
+impl<W: Weight> Display for Delta<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.k {
+            0 => write!(f, "{}", self.c),
+            k if k > 0 => write!(f, "{} - {}δ", self.c, k),
+            k => write!(f, "{} + {}δ", self.c, k.unsigned_abs()),
+        }
+    }
+}
+
+impl<W: Weight> Add for Delta<W> {
+    type Output = Delta<W>;
+    fn add(self, other: Delta<W>) -> Delta<W> {
+        self.checked_add(&other).expect("overflow")
+    }
+}
+
+impl<W: Weight> Sub for Delta<W> {
+    type Output = Delta<W>;
+    fn sub(self, other: Delta<W>) -> Delta<W> {
+        self.checked_sub(&other).expect("overflow")
+    }
+}
+
+impl<W: Weight> Zero for Delta<W> {
+    fn zero() -> Self {
+        Delta::new(W::zero(), 0)
+    }
+    fn is_zero(&self) -> bool {
+        self.c.is_zero() && self.k == 0
+    }
+}
+
+impl<W: Weight> CheckedAdd for Delta<W> {
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(Delta::new(
+            self.c.checked_add(&other.c)?,
+            self.k.checked_add(other.k)?,
+        ))
+    }
+}
+
+impl<W: Weight> CheckedSub for Delta<W> {
+    fn checked_sub(&self, other: &Self) -> Option<Self> {
+        Some(Delta::new(
+            self.c.checked_sub(&other.c)?,
+            self.k.checked_sub(other.k)?,
+        ))
+    }
+}
+
+impl<W: Weight> Weight for Delta<W> {
+    fn strictly_below(self) -> Option<Self> {
+        Some(Delta::new(self.c, self.k.checked_add(1)?))
+    }
+}
+
+impl<W: Weight> From<W> for Delta<W> {
+    fn from(c: W) -> Self {
+        Delta::new(c, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Constraint, DcsError, Solution, DCS};
+
+    #[test]
+    fn test_strict_over_dense_domain() {
+        assert!(Delta::new(0, 1) < Delta::new(0, 0));
+        assert!(Delta::new(-1, -5) < Delta::new(0, 5));
+        assert_eq!(Delta::new(3, 2).to_string(), "3 - 2δ");
+
+        // 0 < 1 - 0 < 1 has no integer solution, but does have a real one.
+        let mut sys = DCS::new();
+        let mut sol = Solution::new();
+        for c in [
+            Constraint::strict(1, 0, Delta::from(1), ()),
+            Constraint::strict(0, 1, Delta::from(0), ()),
+        ] {
+            sol = sys.add_constraint(c, &sol).1.unwrap();
+        }
+        assert!(sys.check_solution(&sol));
+        assert_eq!(sys.get_implied_ub(&1, &0, &sol), Ok(Some(Delta::new(1, 1))));
+        assert_eq!(sys.get_implied_ub(&0, &1, &sol), Ok(Some(Delta::new(0, 1))));
+
+        // closing a zero weight cycle with a strict edge leaves a cycle of weight -δ.
+        let (_, result) = sys.add_constraint(Constraint::new(0, 1, Delta::from(-1), ()), &sol);
+        let Err(DcsError::Infeasible(cycle)) = result else {
+            panic!("expected a negative cycle");
+        };
+        assert_eq!(cycle.weight, Delta::new(0, 1));
+    }
+}
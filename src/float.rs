@@ -0,0 +1,155 @@
+use crate::Weight;
+use num_traits::{CheckedAdd, CheckedSub, Zero};
+use std::cmp::Ordering;
+use std::fmt::Display;
+use std::hash::{Hash, Hasher};
+use std::ops::{Add, Sub};
+
+// an f64 that can be used as a weight. it is totally ordered (0.0 and -0.0 are equal),
+// and an operation that doesn't give a finite result counts as an overflow.
+// rounding errors can make a zero weight cycle look negative, so it is best used with `DCS::with_tolerance`.
+#[derive(Clone, Copy, Debug)]
+pub struct Float(pub f64);
+
+impl PartialEq for Float {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Float {}
+
+impl PartialOrd for Float {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Float {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self.0 == other.0 {
+            Ordering::Equal
+        } else {
+            self.0.total_cmp(&other.0)
+        }
+    }
+}
+
+impl Hash for Float {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // adding 0.0 turns -0.0 into 0.0, which it is equal to.
+        (self.0 + 0.0).to_bits().hash(state);
+    }
+}
+
+impl Display for Float {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Add for Float {
+    type Output = Float;
+    fn add(self, other: Float) -> Float {
+        Float(self.0 + other.0)
+    }
+}
+
+impl Sub for Float {
+    type Output = Float;
+    fn sub(self, other: Float) -> Float {
+        Float(self.0 - other.0)
+    }
+}
+
+impl Zero for Float {
+    fn zero() -> Self {
+        Float(0.0)
+    }
+    fn is_zero(&self) -> bool {
+        self.0 == 0.0
+    }
+}
+
+impl CheckedAdd for Float {
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(*self + *other).filter(|sum| sum.0.is_finite())
+    }
+}
+
+impl CheckedSub for Float {
+    fn checked_sub(&self, other: &Self) -> Option<Self> {
+        Some(*self - *other).filter(|diff| diff.0.is_finite())
+    }
+}
+
+impl Weight for Float {
+    fn strictly_below(self) -> Option<Self> {
+        // the next float down. for an exact treatment of strict constraints over the reals, use `delta::Delta`.
+        Some(Float(self.0.next_down())).filter(|below| below.0.is_finite())
+    }
+}
+
+impl From<f64> for Float {
+    fn from(x: f64) -> Self {
+        Float(x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Constraint, DcsError, Solution, DCS};
+
+    #[test]
+    fn test_tolerance() {
+        // each of these cycles has a weight of exactly 0, which rounding may turn slightly negative.
+        let cycles = [
+            [(1, 0, 0.3), (2, 1, -0.1), (0, 2, -0.2)],
+            [(1, 0, 0.1), (2, 1, 0.2), (0, 2, -0.3)],
+            [(1, 0, -0.7), (2, 1, 0.4), (0, 2, 0.3)],
+        ];
+        for cycle in cycles {
+            for first in 0..3 {
+                let mut sys = DCS::with_tolerance(Float(1e-9));
+                let mut sol = Solution::new();
+                for i in 0..3 {
+                    let (v, u, c) = cycle[(first + i) % 3];
+                    sol = sys
+                        .add_constraint(Constraint::new(v, u, Float(c), ()), &sol)
+                        .1
+                        .unwrap();
+                }
+                assert!(sys.is_feasible());
+                assert!(sys.check_solution(&sol));
+                let (v, u, c) = cycle[first];
+                let (_, result) =
+                    sys.add_constraint(Constraint::new(v, u, Float(c - 1e-6), ()), &sol);
+                let Err(DcsError::Infeasible(certificate)) = result else {
+                    panic!("expected a negative cycle");
+                };
+                assert!((certificate.weight.0 + 1e-6).abs() < 1e-9);
+            }
+            let constraints = cycle.map(|(v, u, c)| Constraint::new(v, u, Float(c), ()));
+            for (sys, sol) in [
+                DCS::from_scratch_with_tolerance(constraints.clone().into_iter(), Float(1e-9)),
+                DCS::from_scratch_batch_with_tolerance(
+                    constraints.clone().into_iter(),
+                    Float(1e-9),
+                ),
+            ] {
+                assert!(sys.is_feasible());
+                assert!(sys.check_solution(&sol));
+            }
+        }
+
+        let sol: Solution<usize, Float> = [(0, Float(0.0)), (1, Float(0.3 + 1e-12))]
+            .into_iter()
+            .collect();
+        assert!(!sol.check_constraint(&Constraint::new(1, 0, Float(0.3), ())));
+        assert!(sol
+            .check_constraint_with_tolerance(&Constraint::new(1, 0, Float(0.3), ()), Float(1e-9)));
+        assert_eq!(Float(0.0), Float(-0.0));
+        assert_eq!(Float(f64::MAX).checked_add(&Float(f64::MAX)), None);
+    }
+}
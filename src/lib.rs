@@ -1,9 +1,22 @@
-use pathfinding::prelude::dijkstra;
+use network::MinimalNetwork;
+use num_traits::{CheckedAdd, CheckedSub, Zero};
+use pathfinding::prelude::{dijkstra, dijkstra_all};
 use priority_queue::PriorityQueue;
 use std::cmp::Reverse;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt::{Debug, Display};
 use std::hash::Hash;
+use std::ops::{Add, Index, Sub};
+
+pub mod delta;
+pub mod float;
+pub mod marco;
+pub mod network;
+pub mod origin;
+#[cfg(feature = "rational")]
+pub mod rational;
+pub mod solver;
+pub mod theory;
 
 pub trait VarId: Eq + Hash + Debug + Clone + Display {}
 impl<T> VarId for T where T: Eq + Hash + Debug + Clone + Display {}
@@ -11,45 +24,294 @@ impl<T> VarId for T where T: Eq + Hash + Debug + Clone + Display {}
 pub trait ConstraintTag: Eq + Hash + Debug + Clone {}
 impl<C> ConstraintTag for C where C: Eq + Hash + Debug + Clone {}
 
+// bounds and values. any ordered additive group works, e.g. i32, i64, i128 or an exact rational type.
+// a type of your own only needs `impl Weight for MyType {}`. rationals are covered by the `rational` feature.
+pub trait Weight: Copy + Ord + Hash + Debug + Display + Zero + CheckedAdd + CheckedSub {
+    // the largest value below self, used for strict constraints: c - 1 for integers, and c - δ for `delta::Delta`.
+    // None if there is no such value. a type that leaves it out (e.g. a dense one) doesn't support strict
+    // constraints, and they are rejected with `DcsError::StrictUnsupported`. use `delta::Delta` for those.
+    fn strictly_below(self) -> Option<Self> {
+        None
+    }
+    fn supports_strict() -> bool {
+        // there is always a value below zero if there is one below anything.
+        Self::zero().strictly_below().is_some()
+    }
+}
+
+macro_rules! integer_weight {
+    ($($t:ty),*) => {
+        $(impl Weight for $t {
+            fn strictly_below(self) -> Option<Self> {
+                self.checked_sub(1)
+            }
+        })*
+    };
+}
+integer_weight!(i8, i16, i32, i64, i128, isize);
+
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
-pub struct Constraint<T: VarId, C: ConstraintTag> {
-    // v - u <= c
+pub struct Constraint<T: VarId, C: ConstraintTag, W: Weight = i64> {
+    // v - u <= c, or v - u < c if strict
     pub v: T,
     pub u: T,
-    pub c: i64,
+    pub c: W,
+    pub strict: bool,
     pub tag: C,
 }
 
-impl<T: VarId, C: ConstraintTag> Display for Constraint<T, C> {
+impl<T: VarId, C: ConstraintTag, W: Weight> Constraint<T, C, W> {
+    pub fn new(v: T, u: T, c: W, tag: C) -> Self {
+        // v - u <= c
+        Constraint {
+            v,
+            u,
+            c,
+            strict: false,
+            tag,
+        }
+    }
+    pub fn strict(v: T, u: T, c: W, tag: C) -> Self {
+        // v - u < c
+        Constraint {
+            v,
+            u,
+            c,
+            strict: true,
+            tag,
+        }
+    }
+    fn weight(&self) -> Cost<W> {
+        // the bound of the equivalent non-strict constraint.
+        weight(self.c, self.strict)
+    }
+    fn check_strict(&self) -> DcsResult<(), T, C, W> {
+        if self.strict && !W::supports_strict() {
+            return Err(DcsError::StrictUnsupported);
+        }
+        Ok(())
+    }
+}
+
+impl<T: VarId, C: ConstraintTag, W: Weight> Display for Constraint<T, C, W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let relation = if self.strict { "<" } else { "<=" };
+        write!(f, "{} - {} {} {}", self.v, self.u, relation, self.c)
+    }
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct ConstraintId(usize);
+
+// the two constraints of an interval, v - u <= hi and u - v <= -lo, which are added and removed together.
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct IntervalId(ConstraintId, ConstraintId);
+
+// a part of a conflict: a constraint, or an interval one or both of whose halves are on the cycle.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum CycleElement<T: VarId, C: ConstraintTag, W: Weight = i64> {
+    Constraint(Constraint<T, C, W>),
+    // lo <= v - u <= hi
+    Interval {
+        id: IntervalId,
+        v: T,
+        u: T,
+        lo: W,
+        hi: W,
+        tag: C,
+    },
+}
+
+impl<T: VarId, C: ConstraintTag, W: Weight> Display for CycleElement<T, C, W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CycleElement::Constraint(constraint) => write!(f, "{}", constraint),
+            CycleElement::Interval { v, u, lo, hi, .. } => {
+                write!(f, "{} <= {} - {} <= {}", lo, v, u, hi)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct NegativeCycle<T: VarId, C: ConstraintTag, W: Weight = i64> {
+    // constraints[i].v == constraints[i + 1].u, and the last v closes the cycle at the first u.
+    // the first constraint is the one that was rejected.
+    pub constraints: Vec<Constraint<T, C, W>>,
+    pub weight: W,
+    // the same cycle as the user sees it: the halves of an interval are replaced by the interval,
+    // at the position of the first of them.
+    pub elements: Vec<CycleElement<T, C, W>>,
+}
+
+impl<T: VarId, C: ConstraintTag, W: Weight> NegativeCycle<T, C, W> {
+    fn from_constraints(constraints: Vec<Constraint<T, C, W>>) -> DcsResult<Self, T, C, W> {
+        let weight = constraints
+            .iter()
+            .fold(Cost::zero(), |weight, constraint| {
+                weight + constraint.weight()
+            })
+            .get()?;
+        let elements = constraints
+            .iter()
+            .cloned()
+            .map(CycleElement::Constraint)
+            .collect();
+        Ok(NegativeCycle {
+            constraints,
+            weight,
+            elements,
+        })
+    }
+    pub fn tags(&self) -> impl Iterator<Item = &C> + '_ {
+        self.constraints.iter().map(|constraint| &constraint.tag)
+    }
+}
+
+impl<T: VarId, C: ConstraintTag, W: Weight> Display for NegativeCycle<T, C, W> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} - {} <= {}", self.v, self.u, self.c)
+        for element in self.elements.iter() {
+            write!(f, "{}, ", element)?;
+        }
+        write!(f, "sum of bounds: {}", self.weight)
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum DcsError<T: VarId, C: ConstraintTag, W: Weight = i64> {
+    Infeasible(NegativeCycle<T, C, W>),
+    UnknownConstraintId(ConstraintId),
+    UnknownConstraint(Constraint<T, C, W>),
+    UnknownVariable(T),
+    Overflow,
+    // a strict constraint over a weight type without `strictly_below`.
+    StrictUnsupported,
+}
+
+impl<T: VarId, C: ConstraintTag, W: Weight> Display for DcsError<T, C, W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DcsError::Infeasible(cycle) => write!(f, "infeasible: {}", cycle),
+            DcsError::UnknownConstraintId(id) => write!(f, "unknown constraint id: {:?}", id),
+            DcsError::UnknownConstraint(constraint) => {
+                write!(f, "unknown constraint: {}", constraint)
+            }
+            DcsError::UnknownVariable(var) => write!(f, "unknown variable: {}", var),
+            DcsError::Overflow => write!(f, "arithmetic overflow"),
+            DcsError::StrictUnsupported => {
+                write!(
+                    f,
+                    "strict constraints are not supported by this weight type"
+                )
+            }
+        }
+    }
+}
+
+impl<T: VarId, C: ConstraintTag, W: Weight> std::error::Error for DcsError<T, C, W> {}
+
+pub type DcsResult<R, T, C, W = i64> = Result<R, DcsError<T, C, W>>;
+
+// errors of operations on a solution alone, which don't involve any constraint.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum SolutionError<T: VarId> {
+    UnknownVariable(T),
+    Overflow,
+}
+
+impl<T: VarId> Display for SolutionError<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SolutionError::UnknownVariable(var) => write!(f, "unknown variable: {}", var),
+            SolutionError::Overflow => write!(f, "arithmetic overflow"),
+        }
+    }
+}
+
+impl<T: VarId> std::error::Error for SolutionError<T> {}
+
+impl<T: VarId, C: ConstraintTag, W: Weight> From<SolutionError<T>> for DcsError<T, C, W> {
+    fn from(err: SolutionError<T>) -> Self {
+        match err {
+            SolutionError::UnknownVariable(var) => DcsError::UnknownVariable(var),
+            SolutionError::Overflow => DcsError::Overflow,
+        }
     }
 }
 
 #[derive(Debug, Clone)]
-pub struct Solution<T: VarId>(HashMap<T, i64>);
+pub struct Solution<T: VarId, W: Weight = i64>(HashMap<T, W>);
 
-impl<T: VarId> Solution<T> {
-    pub fn new() -> Solution<T> {
+impl<T: VarId, W: Weight> Solution<T, W> {
+    pub fn new() -> Solution<T, W> {
         let map = HashMap::new();
         Solution(map)
     }
-    fn update(&mut self, var: &T, val: i64) {
+    fn update(&mut self, var: &T, val: W) {
         self.0.insert(var.clone(), val);
     }
-    pub fn get_or(&self, var: &T, default: i64) -> i64 {
+    pub fn get_or(&self, var: &T, default: W) -> W {
         *self.get(var).unwrap_or(&default)
     }
-    fn get(&self, var: &T) -> Option<&i64> {
+    pub fn get(&self, var: &T) -> Option<&W> {
         self.0.get(var)
     }
-    pub fn check_constraint<C: ConstraintTag>(&self, constraint: &Constraint<T, C>) -> bool {
+    pub fn iter(&self) -> impl Iterator<Item = (&T, &W)> + '_ {
+        self.0.iter()
+    }
+    pub fn variables(&self) -> impl Iterator<Item = &T> + '_ {
+        self.0.keys()
+    }
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    pub fn normalize(&mut self, reference: Option<&T>) -> Result<(), SolutionError<T>> {
+        // shifts every value so that the reference (or the smallest value, if there is no reference) is zero.
+        // a shift doesn't change any difference, so the solution stays valid.
+        // on error nothing changes.
+        let shift = match reference {
+            Some(var) => *self
+                .get(var)
+                .ok_or_else(|| SolutionError::UnknownVariable(var.clone()))?,
+            None => match self.0.values().min() {
+                Some(shift) => *shift,
+                None => return Ok(()),
+            },
+        };
+        let mut shifted = HashMap::with_capacity(self.0.len());
+        for (var, val) in self.0.iter() {
+            let val = val.checked_sub(&shift).ok_or(SolutionError::Overflow)?;
+            shifted.insert(var.clone(), val);
+        }
+        self.0 = shifted;
+        Ok(())
+    }
+    fn remove(&mut self, var: &T) {
+        self.0.remove(var);
+    }
+    pub fn check_constraint<C: ConstraintTag>(&self, constraint: &Constraint<T, C, W>) -> bool {
+        self.check_constraint_with_tolerance(constraint, W::zero())
+    }
+    pub fn check_constraint_with_tolerance<C: ConstraintTag>(
+        &self,
+        constraint: &Constraint<T, C, W>,
+        tolerance: W,
+    ) -> bool {
+        // v - u <= c + tolerance (or < if strict)
         if let (Some(u), Some(v)) = (self.get(&constraint.u), self.get(&constraint.v)) {
-            return v - u <= constraint.c;
+            let bound = Cost::from(constraint.c) + Cost::from(tolerance);
+            // if v - u overflows, its sign alone decides.
+            return v.checked_sub(u).map_or(v < u, |d| match constraint.strict {
+                true => Cost::from(d) < bound,
+                false => Cost::from(d) <= bound,
+            });
         }
         true
     }
-    pub fn merge(&mut self, other: &Solution<T>) {
+    pub fn merge(&mut self, other: &Solution<T, W>) {
         // todo: can consume other to avoid clones?
         for (key, val) in other.0.iter() {
             self.0.entry(key.clone()).or_insert(*val);
@@ -57,274 +319,1717 @@ impl<T: VarId> Solution<T> {
     }
 }
 
-impl<T: VarId> FromIterator<(T, i64)> for Solution<T> {
-    fn from_iter<I: IntoIterator<Item = (T, i64)>>(iter: I) -> Self {
+impl<T: VarId, W: Weight> FromIterator<(T, W)> for Solution<T, W> {
+    fn from_iter<I: IntoIterator<Item = (T, W)>>(iter: I) -> Self {
         Solution(HashMap::from_iter(iter))
     }
 }
 
-impl<T: VarId> Default for Solution<T> {
+impl<T: VarId, W: Weight> Index<&T> for Solution<T, W> {
+    type Output = W;
+    fn index(&self, var: &T) -> &W {
+        &self.0[var]
+    }
+}
+
+impl<T: VarId, W: Weight> IntoIterator for Solution<T, W> {
+    type Item = (T, W);
+    type IntoIter = std::collections::hash_map::IntoIter<T, W>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a, T: VarId, W: Weight> IntoIterator for &'a Solution<T, W> {
+    type Item = (&'a T, &'a W);
+    type IntoIter = std::collections::hash_map::Iter<'a, T, W>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<T: VarId, W: Weight> Default for Solution<T, W> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-struct FromEdges<T: VarId, C: ConstraintTag>(HashMap<T, PriorityQueue<(i64, C), Reverse<i64>>>);
-impl<T: VarId, C: ConstraintTag> FromEdges<T, C> {
+// the values an operation changed: (var, old value, new value), where the old value is None if var had none.
+// a variable that changed more than once shows up once, with its first old value and its last new value.
+#[derive(Debug, Clone)]
+pub struct SolutionDelta<T: VarId, W: Weight = i64>(Vec<(T, Option<W>, W)>);
+
+impl<T: VarId, W: Weight> SolutionDelta<T, W> {
+    pub fn new() -> SolutionDelta<T, W> {
+        SolutionDelta(vec![])
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&T, Option<W>, W)> + '_ {
+        self.0.iter().map(|(var, old, new)| (var, *old, *new))
+    }
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    pub fn apply(&self, sol: &mut Solution<T, W>) {
+        for (var, _, new) in self.0.iter() {
+            sol.update(var, *new);
+        }
+    }
+    pub fn revert(&self, sol: &mut Solution<T, W>) {
+        for (var, old, _) in self.0.iter() {
+            match old {
+                Some(old) => sol.update(var, *old),
+                None => sol.remove(var),
+            }
+        }
+    }
+}
+
+impl<T: VarId, W: Weight> Default for SolutionDelta<T, W> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// path lengths for the shortest path searches. a sum that overflows is larger than any finite one,
+// so a search only ends up with it if there is no other way. one that underflows is smaller than any finite one.
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug)]
+enum Cost<W: Weight> {
+    Underflow,
+    Finite(W),
+    Overflow,
+}
+
+impl<W: Weight> Cost<W> {
+    fn get<T: VarId, C: ConstraintTag>(self) -> DcsResult<W, T, C, W> {
+        match self {
+            Cost::Finite(x) => Ok(x),
+            Cost::Underflow | Cost::Overflow => Err(DcsError::Overflow),
+        }
+    }
+    fn out_of_range(up: bool) -> Self {
+        match up {
+            true => Cost::Overflow,
+            false => Cost::Underflow,
+        }
+    }
+}
+
+impl<W: Weight> From<W> for Cost<W> {
+    fn from(x: W) -> Self {
+        Cost::Finite(x)
+    }
+}
+
+// a sum of an overflow and an underflow could be anything. it is taken as an underflow,
+// which never passes for a bound that holds.
+impl<W: Weight> Add for Cost<W> {
+    type Output = Cost<W>;
+    fn add(self, other: Cost<W>) -> Cost<W> {
+        match (self, other) {
+            (Cost::Finite(a), Cost::Finite(b)) => a
+                .checked_add(&b)
+                .map_or_else(|| Cost::out_of_range(b > W::zero()), Cost::Finite),
+            (Cost::Underflow, _) | (_, Cost::Underflow) => Cost::Underflow,
+            _ => Cost::Overflow,
+        }
+    }
+}
+
+impl<W: Weight> Sub for Cost<W> {
+    type Output = Cost<W>;
+    fn sub(self, other: Cost<W>) -> Cost<W> {
+        match (self, other) {
+            (Cost::Finite(a), Cost::Finite(b)) => a
+                .checked_sub(&b)
+                .map_or_else(|| Cost::out_of_range(b < W::zero()), Cost::Finite),
+            (Cost::Underflow, _) | (_, Cost::Overflow) => Cost::Underflow,
+            _ => Cost::Overflow,
+        }
+    }
+}
+
+impl<W: Weight> Zero for Cost<W> {
+    fn zero() -> Self {
+        Cost::Finite(W::zero())
+    }
+    fn is_zero(&self) -> bool {
+        *self == Cost::zero()
+    }
+}
+
+fn weight<W: Weight>(c: W, strict: bool) -> Cost<W> {
+    match strict {
+        // without a value below c, the bound is out of range from below, and can't pass for one that holds.
+        true => c.strictly_below().map_or(Cost::Underflow, Cost::Finite),
+        false => Cost::Finite(c),
+    }
+}
+
+// a constraint without its variables.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+struct Edge<C: ConstraintTag, W: Weight> {
+    c: W,
+    strict: bool,
+    tag: C,
+}
+impl<C: ConstraintTag, W: Weight> Edge<C, W> {
+    fn to_constraint<T: VarId>(&self, v: &T, u: &T) -> Constraint<T, C, W> {
+        Constraint {
+            v: v.clone(),
+            u: u.clone(),
+            c: self.c,
+            strict: self.strict,
+            tag: self.tag.clone(),
+        }
+    }
+}
+
+// edges are ordered by their weight, i.e. the bound of the equivalent non-strict constraint.
+type EdgeHeap<C, W> = PriorityQueue<Edge<C, W>, Reverse<Cost<W>>>;
+struct FromEdges<T: VarId, C: ConstraintTag, W: Weight>(HashMap<T, EdgeHeap<C, W>>);
+impl<T: VarId, C: ConstraintTag, W: Weight> FromEdges<T, C, W> {
     fn new() -> Self {
         FromEdges(HashMap::new())
     }
     fn is_empty(&self) -> bool {
         self.0.values().all(|a| a.is_empty()) // todo: cahce
     }
-    fn to_pairs(&self) -> impl Iterator<Item = (&T, &i64, &C)> + '_ {
+    fn to_pairs(&self) -> impl Iterator<Item = (&T, Cost<W>, &Edge<C, W>)> + '_ {
         self.0.iter().filter_map(|(var, heap)| {
             // todo: use Option.map instead if match
-            if let Some(((val, tag), _)) = heap.peek() {
-                Some((var, val, tag))
+            if let Some((edge, weight)) = heap.peek() {
+                Some((var, weight.0, edge))
             } else {
                 None
             }
         })
     }
-    fn add(&mut self, var: T, val: i64, tag: C) {
+    fn to_all_pairs(&self) -> impl Iterator<Item = (&T, Cost<W>, &Edge<C, W>)> + '_ {
+        self.0
+            .iter()
+            .flat_map(|(var, heap)| heap.iter().map(move |(edge, weight)| (var, weight.0, edge)))
+    }
+    fn add(&mut self, var: T, edge: Edge<C, W>) -> bool {
+        let weight = weight(edge.c, edge.strict);
         self.0
             .entry(var)
             .or_default()
-            .push((val, tag), Reverse(val));
+            .push(edge, Reverse(weight))
+            .is_none()
+    }
+    fn get(&self, var: &T) -> Option<(Cost<W>, &Edge<C, W>)> {
+        let (edge, weight) = self.0.get(var)?.peek()?;
+        Some((weight.0, edge))
     }
-    fn remove(&mut self, var: &T, val: i64, tag: C) -> bool {
+    fn get_all(&self, var: &T) -> impl Iterator<Item = (Cost<W>, &Edge<C, W>)> + '_ {
+        self.0
+            .get(var)
+            .into_iter()
+            .flat_map(|heap| heap.iter().map(|(edge, weight)| (weight.0, edge)))
+    }
+    fn remove(&mut self, var: &T, edge: Edge<C, W>) -> bool {
         if let Some(heap) = self.0.get_mut(var) {
-            return heap.remove(&(val, tag)).is_some();
+            return heap.remove(&edge).is_some();
             // return heap.remove(val);
         };
         false
     }
 }
-impl<T: VarId, C: ConstraintTag> Default for FromEdges<T, C> {
+impl<T: VarId, C: ConstraintTag, W: Weight> Default for FromEdges<T, C, W> {
     fn default() -> Self {
         Self::new()
     }
 }
-struct Edges<T: VarId, C: ConstraintTag>(HashMap<T, FromEdges<T, C>>);
-impl<T: VarId, C: ConstraintTag> Edges<T, C> {
+// edges keyed by u, and the set of every u with an edge into each v.
+struct Edges<T: VarId, C: ConstraintTag, W: Weight>(
+    HashMap<T, FromEdges<T, C, W>>,
+    HashMap<T, HashSet<T>>,
+);
+impl<T: VarId, C: ConstraintTag, W: Weight> Edges<T, C, W> {
     fn new() -> Self {
-        Edges(HashMap::new())
+        Edges(HashMap::new(), HashMap::new())
     }
     fn is_empty(&self) -> bool {
         self.0.values().all(|a| a.is_empty()) // todo: cahce
     }
-    fn to_constraints(&self) -> impl Iterator<Item = Constraint<T, C>> + '_ {
+    fn contains_var(&self, var: &T) -> bool {
+        self.0.get(var).is_some_and(|from_var| !from_var.is_empty())
+            || self.1.get(var).is_some_and(|to_var| !to_var.is_empty())
+    }
+    fn variables(&self) -> HashSet<&T> {
+        self.0
+            .keys()
+            .chain(self.1.keys())
+            .filter(|var| self.contains_var(var))
+            .collect()
+    }
+    fn to_constraints(&self) -> impl Iterator<Item = Constraint<T, C, W>> + '_ {
         self.0.iter().flat_map(|(u, from_edges)| {
-            from_edges.to_pairs().map(|(v, c, tag)| Constraint {
-                v: v.clone(),
-                u: u.clone(),
-                c: *c,
-                tag: tag.clone(),
-            })
+            from_edges
+                .to_pairs()
+                .map(|(v, _, edge)| edge.to_constraint(v, u))
+        })
+    }
+    fn to_all_constraints(&self) -> impl Iterator<Item = Constraint<T, C, W>> + '_ {
+        // unlike to_constraints, includes constraints that are dominated by a tighter one on the same pair.
+        self.0.iter().flat_map(|(u, from_edges)| {
+            from_edges
+                .to_all_pairs()
+                .map(|(v, _, edge)| edge.to_constraint(v, u))
         })
     }
-    fn add(&mut self, constraint: Constraint<T, C>) {
+    fn constraints_to<'a>(&'a self, var: &'a T) -> impl Iterator<Item = Constraint<T, C, W>> + 'a {
+        // every constraint var - u <= c, found through the index of the edges into var.
+        self.1.get(var).into_iter().flatten().flat_map(move |u| {
+            self.0
+                .get(u)
+                .into_iter()
+                .flat_map(move |from_u| from_u.get_all(var))
+                .map(move |(_, edge)| edge.to_constraint(var, u))
+        })
+    }
+    fn constraints_from<'a>(
+        &'a self,
+        var: &'a T,
+    ) -> impl Iterator<Item = Constraint<T, C, W>> + 'a {
+        // every constraint v - var <= c.
+        self.0.get(var).into_iter().flat_map(move |from_var| {
+            from_var
+                .to_all_pairs()
+                .map(move |(v, _, edge)| edge.to_constraint(v, var))
+        })
+    }
+    fn predecessors<'a>(
+        &'a self,
+        var: &'a T,
+    ) -> impl Iterator<Item = (&'a T, Cost<W>, &'a Edge<C, W>)> + 'a {
+        self.1.get(var).into_iter().flatten().filter_map(move |u| {
+            let (weight, edge) = self.0.get(u)?.get(var)?;
+            Some((u, weight, edge))
+        })
+    }
+    fn add(&mut self, constraint: Constraint<T, C, W>) -> bool {
+        // returns false if the constraint was already there.
+        self.1
+            .entry(constraint.v.clone())
+            .or_default()
+            .insert(constraint.u.clone());
+        let edge = Edge {
+            c: constraint.c,
+            strict: constraint.strict,
+            tag: constraint.tag,
+        };
         self.0
             .entry(constraint.u)
             .or_default()
-            .add(constraint.v, constraint.c, constraint.tag);
+            .add(constraint.v, edge)
     }
-    fn remove(&mut self, constraint: Constraint<T, C>) -> bool {
-        if let Some(from_u) = self.0.get_mut(&constraint.u) {
-            return from_u.remove(&constraint.v, constraint.c, constraint.tag);
+    fn remove(&mut self, constraint: Constraint<T, C, W>) -> bool {
+        let Some(from_u) = self.0.get_mut(&constraint.u) else {
+            return false;
         };
-        false
+        let edge = Edge {
+            c: constraint.c,
+            strict: constraint.strict,
+            tag: constraint.tag,
+        };
+        if !from_u.remove(&constraint.v, edge) {
+            return false;
+        }
+        if from_u.get(&constraint.v).is_none() {
+            // that was the last edge from u to v.
+            if let Some(to_v) = self.1.get_mut(&constraint.v) {
+                to_v.remove(&constraint.u);
+            }
+        }
+        true
     }
 }
-pub struct DCS<T: VarId, C: ConstraintTag> {
-    feasible_constraints: Edges<T, C>,
-    infeasible_constraints: Edges<T, C>,
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Status {
+    Feasible,
+    Infeasible,
+    Undetermined,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum WatchEvent<T: VarId, C: ConstraintTag, W: Weight = i64> {
+    // the watched constraint is implied by the feasible constraints.
+    Implied(Constraint<T, C, W>),
+    // the watched constraint would close a negative cycle with the feasible constraints.
+    Impossible(Constraint<T, C, W>),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Store {
+    Feasible,
+    Infeasible,
+    Undetermined,
+    Watched,
 }
 
-impl<T: VarId, C: ConstraintTag> DCS<T, C> {
+enum TrailEntry<T: VarId, C: ConstraintTag, W: Weight> {
+    Added(Store, Constraint<T, C, W>),
+    Removed(Store, Constraint<T, C, W>),
+    // the value the variable had before it was changed (None if it had no value).
+    Assigned(T, Option<W>),
+    Registered(ConstraintId),
+    Unregistered(ConstraintId, Constraint<T, C, W>),
+}
+
+// a chain of constraints, each starting where the previous one ends.
+pub type Path<T, C, W = i64> = Vec<Constraint<T, C, W>>;
+
+pub struct DCS<T: VarId, C: ConstraintTag, W: Weight = i64> {
+    feasible_constraints: Edges<T, C, W>,
+    infeasible_constraints: Edges<T, C, W>,
+    // constraints that were added with `add` and not yet checked by `solve`.
+    undetermined_constraints: Edges<T, C, W>,
+    // constraints that are not part of the system, and are reported once they become implied or impossible.
+    watched_constraints: Edges<T, C, W>,
+    watch_events: Vec<WatchEvent<T, C, W>>,
+    // handles given out by add_constraint and add. handles of identical constraints share a single edge,
+    // which is dropped with the last of them.
+    handles: HashMap<ConstraintId, Constraint<T, C, W>>,
+    ids: HashMap<Constraint<T, C, W>, Vec<ConstraintId>>,
+    next_id: usize,
+    // the handles of interval halves, which only go through the interval api. ids aren't reused,
+    // so an entry can outlive its handle (e.g. one undone by pop).
+    intervals: HashMap<ConstraintId, IntervalId>,
+    // every change since the oldest open scope, and the trail length at the start of each scope.
+    trail: Vec<TrailEntry<T, C, W>>,
+    scopes: Vec<usize>,
+    // how much a constraint may be violated by. zero unless rounding errors are expected.
+    tolerance: W,
+    // the all-pairs bounds of the feasible constraints, if they are maintained. additions and tightenings update
+    // it in place, removals make it stale until it is recomputed once at the end of the operation.
+    // an addition whose bounds overflow also makes it stale, and it stays stale until the next recompute
+    // (a removal, relaxation or pop) succeeds.
+    network: Option<MinimalNetwork<T, W>>,
+    network_stale: bool,
+}
+
+impl<T: VarId, C: ConstraintTag, W: Weight> DCS<T, C, W> {
     pub fn new() -> Self {
         DCS {
             feasible_constraints: Edges::new(),
             infeasible_constraints: Edges::new(),
+            undetermined_constraints: Edges::new(),
+            watched_constraints: Edges::new(),
+            watch_events: vec![],
+            handles: HashMap::new(),
+            ids: HashMap::new(),
+            next_id: 0,
+            intervals: HashMap::new(),
+            trail: vec![],
+            scopes: vec![],
+            tolerance: W::zero(),
+            network: None,
+            network_stale: false,
+        }
+    }
+    pub fn with_tolerance(tolerance: W) -> Self {
+        // cycles that are negative by no more than the tolerance are accepted, and values that
+        // violate a constraint by no more than it are left as they are.
+        DCS {
+            tolerance,
+            ..Self::new()
+        }
+    }
+    pub fn status(&self) -> Status {
+        if !self.infeasible_constraints.is_empty() {
+            Status::Infeasible
+        } else if self.undetermined_constraints.is_empty() {
+            Status::Feasible
+        } else {
+            Status::Undetermined
         }
     }
     pub fn is_feasible(&self) -> bool {
-        self.infeasible_constraints.is_empty()
+        self.status() == Status::Feasible
     }
-    pub fn from_scratch<It>(constraints: It) -> (Self, Solution<T>)
+    pub fn from_scratch<It>(constraints: It) -> (Self, Solution<T, W>)
     where
-        It: Iterator<Item = Constraint<T, C>>,
+        It: Iterator<Item = Constraint<T, C, W>>,
     {
-        let mut sys = Self::new();
+        Self::from_scratch_with_tolerance(constraints, W::zero())
+    }
+    pub fn from_scratch_with_tolerance<It>(constraints: It, tolerance: W) -> (Self, Solution<T, W>)
+    where
+        It: Iterator<Item = Constraint<T, C, W>>,
+    {
+        let mut sys = Self::with_tolerance(tolerance);
         let mut sol = Solution::new();
         for constraint in constraints {
-            if let Some(new_sol) = sys.add_constraint(constraint, &sol) {
+            if let (_, Ok(new_sol)) = sys.add_constraint(constraint, &sol) {
                 sol = new_sol;
             };
         }
         (sys, sol)
     }
-    pub fn all_infeasible_constraints(&self) -> impl Iterator<Item = Constraint<T, C>> + '_ {
+    pub fn from_scratch_batch<It>(constraints: It) -> (Self, Solution<T, W>)
+    where
+        It: Iterator<Item = Constraint<T, C, W>>,
+    {
+        Self::from_scratch_batch_with_tolerance(constraints, W::zero())
+    }
+    pub fn from_scratch_batch_with_tolerance<It>(
+        constraints: It,
+        tolerance: W,
+    ) -> (Self, Solution<T, W>)
+    where
+        It: Iterator<Item = Constraint<T, C, W>>,
+    {
+        // builds the edges in one pass, and finds the potentials with SPFA instead of one search per constraint.
+        // gives the same kind of result as from_scratch: every negative cycle that SPFA finds loses its latest
+        // constraint to the infeasible store, and the search starts over without it.
+        // if a distance overflows, the constraints are added one by one with from_scratch instead.
+        let constraints: Vec<Constraint<T, C, W>> = constraints.collect();
+        let mut sys = Self::with_tolerance(tolerance);
+        for constraint in constraints.iter() {
+            let id = sys.new_id();
+            sys.register(id, constraint.clone());
+            sys.add_to_feasible(constraint.clone());
+        }
+        let mut rejected = vec![];
+        let dist = loop {
+            match sys.potentials() {
+                Ok(dist) => break dist,
+                Err(DcsError::Infeasible(cycle)) => {
+                    let latest = cycle
+                        .constraints
+                        .into_iter()
+                        .max_by_key(|constraint| sys.find_id(constraint))
+                        .unwrap();
+                    sys.remove_from_feasible(latest.clone());
+                    rejected.push(latest);
+                }
+                Err(_) => {
+                    return Self::from_scratch_with_tolerance(constraints.into_iter(), tolerance)
+                }
+            }
+        };
+        let mut sol = dist.into_iter().collect();
+        // a constraint that was dropped for one cycle may fit once the others are dropped as well.
+        rejected.sort_by_key(|constraint| sys.find_id(constraint));
+        for constraint in rejected {
+            let _ = sys.decide(constraint, &mut sol);
+        }
+        (sys, sol)
+    }
+    fn potentials(&self) -> DcsResult<HashMap<T, W>, T, C, W> {
+        // a solution of the feasible constraints, from a single run of SPFA (queue based bellman-ford)
+        // from a virtual source connected to every variable with a 0 weight edge.
+        // a distance is only lowered by more than the tolerance, so the cycles it finds are below -tolerance.
+        let tolerance = Cost::from(self.tolerance);
+        let mut dist: HashMap<T, W> = HashMap::new();
+        for constraint in self.handles.values() {
+            dist.insert(constraint.u.clone(), W::zero());
+            dist.insert(constraint.v.clone(), W::zero());
+        }
+        let num_vars = dist.len();
+        let mut q: VecDeque<T> = dist.keys().cloned().collect();
+        let mut in_queue: HashSet<T> = dist.keys().cloned().collect();
+        let mut num_edges: HashMap<T, usize> = HashMap::new();
+        let mut parents: HashMap<T, (T, Edge<C, W>)> = HashMap::new();
+        while let Some(x) = q.pop_front() {
+            in_queue.remove(&x);
+            let Some(succesors) = self.feasible_constraints.0.get(&x) else {
+                continue;
+            };
+            let d_x = dist[&x];
+            for (y, w, edge) in succesors.to_pairs() {
+                let d_y = Cost::from(d_x) + w;
+                if d_y + tolerance >= Cost::from(dist[y]) {
+                    continue;
+                }
+                let d_y = d_y.get()?;
+                dist.insert(y.clone(), d_y);
+                parents.insert(y.clone(), (x.clone(), edge.clone()));
+                let n = num_edges.get(&x).unwrap_or(&0) + 1;
+                num_edges.insert(y.clone(), n);
+                if n >= num_vars {
+                    // a path with num_vars edges got shorter, so there is a negative cycle.
+                    // once it shows up in the parents graph it can be reported.
+                    if let Some(cycle) = Self::find_parents_cycle(y, &parents)? {
+                        return Err(DcsError::Infeasible(cycle));
+                    }
+                }
+                if !in_queue.contains(y) {
+                    in_queue.insert(y.clone());
+                    q.push_back(y.clone());
+                }
+            }
+        }
+        Ok(dist)
+    }
+    fn find_parents_cycle(
+        start: &T,
+        parents: &HashMap<T, (T, Edge<C, W>)>,
+    ) -> DcsResult<Option<NegativeCycle<T, C, W>>, T, C, W> {
+        // every cycle in the parents graph of bellman-ford is negative.
+        let mut visited = HashSet::new();
+        let mut node = start;
+        while visited.insert(node) {
+            let Some((parent, _)) = parents.get(node) else {
+                return Ok(None);
+            };
+            node = parent;
+        }
+        let cycle_start = node;
+        let mut constraints = vec![];
+        loop {
+            let (parent, edge) = &parents[node];
+            constraints.push(edge.to_constraint(node, parent));
+            node = parent;
+            if node == cycle_start {
+                break;
+            }
+        }
+        constraints.reverse();
+        NegativeCycle::from_constraints(constraints).map(Some)
+    }
+    pub fn all_infeasible_constraints(&self) -> impl Iterator<Item = Constraint<T, C, W>> + '_ {
         self.infeasible_constraints.to_constraints()
     }
-    pub fn all_feasible_constraints(&self) -> impl Iterator<Item = Constraint<T, C>> + '_ {
+    pub fn all_feasible_constraints(&self) -> impl Iterator<Item = Constraint<T, C, W>> + '_ {
         self.feasible_constraints.to_constraints()
     }
-    pub fn check_solution(&self, sol: &Solution<T>) -> bool {
+    pub fn predecessors<'a>(&'a self, x: &'a T) -> impl Iterator<Item = Constraint<T, C, W>> + 'a {
+        // the feasible constraints x - u <= c, i.e. the edges into x.
+        self.feasible_constraints.constraints_to(x)
+    }
+    pub fn successors<'a>(&'a self, x: &'a T) -> impl Iterator<Item = Constraint<T, C, W>> + 'a {
+        // the feasible constraints v - x <= c, i.e. the edges out of x.
+        self.feasible_constraints.constraints_from(x)
+    }
+    pub fn all_undetermined_constraints(&self) -> impl Iterator<Item = Constraint<T, C, W>> + '_ {
+        self.undetermined_constraints.to_all_constraints()
+    }
+    pub fn minimal_conflicts(&self, sol: &Solution<T, W>) -> Vec<(C, Vec<C>)> {
+        // a simple negative cycle is a minimal infeasible subset, since removing any of its constraints
+        // leaves a set of paths. every infeasible constraint closes such a cycle with the feasible ones.
+        self.all_infeasible_constraints()
+            .filter_map(
+                |constraint| match self.check_and_solve_new_constraint(&constraint, sol) {
+                    Err(DcsError::Infeasible(cycle)) => {
+                        Some((constraint.tag, cycle.tags().skip(1).cloned().collect()))
+                    }
+                    _ => None,
+                },
+            )
+            .collect()
+    }
+    pub fn check_solution(&self, sol: &Solution<T, W>) -> bool {
         for constraint in self.all_feasible_constraints() {
-            if !sol.check_constraint(&constraint) {
+            if !sol.check_constraint_with_tolerance(&constraint, self.tolerance) {
                 return false;
             }
         }
         true
     }
-    fn add_to_feasible(&mut self, constraint: Constraint<T, C>) {
-        self.feasible_constraints.add(constraint);
+    pub fn push(&mut self) {
+        self.scopes.push(self.trail.len());
+    }
+    pub fn pop(&mut self, sol: &mut Solution<T, W>) -> bool {
+        // undoes every change made since the matching push, including the changes to sol.
+        // sol must be the latest solution returned by this system.
+        let Some(mark) = self.scopes.pop() else {
+            return false;
+        };
+        for entry in self.trail.split_off(mark).into_iter().rev() {
+            match entry {
+                TrailEntry::Added(store, constraint) => {
+                    self.network_stale |= matches!(store, Store::Feasible);
+                    self.store_mut(store).remove(constraint);
+                }
+                TrailEntry::Removed(store, constraint) => {
+                    self.network_stale |= matches!(store, Store::Feasible);
+                    self.store_mut(store).add(constraint);
+                }
+                TrailEntry::Assigned(var, Some(val)) => sol.update(&var, val),
+                TrailEntry::Assigned(var, None) => sol.remove(&var),
+                TrailEntry::Registered(id) => {
+                    let constraint = self.handles.remove(&id).unwrap();
+                    self.remove_id(&constraint, id);
+                }
+                TrailEntry::Unregistered(id, constraint) => {
+                    self.ids.entry(constraint.clone()).or_default().push(id);
+                    self.handles.insert(id, constraint);
+                }
+            }
+        }
+        self.refresh_network(sol);
+        true
     }
-    fn add_to_infeasible(&mut self, constraint: Constraint<T, C>) {
-        self.infeasible_constraints.add(constraint);
+    fn release(&mut self) {
+        // closes the innermost scope and keeps its changes, which still belong to the enclosing scope (if any).
+        self.scopes.pop();
+        if self.scopes.is_empty() {
+            self.trail.clear();
+        }
+    }
+    fn tracked<R>(
+        &mut self,
+        sol: &mut Solution<T, W>,
+        op: impl FnOnce(&mut Self, &mut Solution<T, W>) -> R,
+    ) -> (R, SolutionDelta<T, W>) {
+        // runs op in a scope of its own, and collects the values it changed from the trail.
+        self.push();
+        let mark = self.trail.len();
+        let result = op(self, sol);
+        let mut delta = SolutionDelta::new();
+        let mut seen = HashSet::new();
+        for entry in self.trail[mark..].iter() {
+            if let TrailEntry::Assigned(var, old) = entry {
+                if seen.insert(var) {
+                    delta
+                        .0
+                        .push((var.clone(), *old, sol.get_or(var, W::zero())));
+                }
+            }
+        }
+        self.release();
+        (result, delta)
+    }
+    fn transaction(
+        &mut self,
+        sol: &mut Solution<T, W>,
+        keep_infeasible: bool,
+        op: impl FnOnce(&mut Self, &mut Solution<T, W>) -> DcsResult<(), T, C, W>,
+    ) -> DcsResult<SolutionDelta<T, W>, T, C, W> {
+        // like tracked, but if op fails everything it did is undone, including its watch events.
+        // with keep_infeasible, a failure that leaves a constraint in the infeasible store is kept as well.
+        let num_events = self.watch_events.len();
+        let (result, delta) = self.tracked(sol, |sys, sol| {
+            sys.push();
+            let result = op(sys, sol);
+            match result {
+                Ok(()) => sys.release(),
+                Err(DcsError::Infeasible(_)) if keep_infeasible => sys.release(),
+                Err(_) => {
+                    sys.pop(sol);
+                    sys.watch_events.truncate(num_events);
+                }
+            }
+            result
+        });
+        result.map(|()| delta)
+    }
+    pub fn num_scopes(&self) -> usize {
+        self.scopes.len()
+    }
+    fn is_recording(&self) -> bool {
+        !self.scopes.is_empty()
+    }
+    fn store_mut(&mut self, store: Store) -> &mut Edges<T, C, W> {
+        match store {
+            Store::Feasible => &mut self.feasible_constraints,
+            Store::Infeasible => &mut self.infeasible_constraints,
+            Store::Undetermined => &mut self.undetermined_constraints,
+            Store::Watched => &mut self.watched_constraints,
+        }
+    }
+    fn add_to(&mut self, store: Store, constraint: Constraint<T, C, W>) {
+        let entry = self.is_recording().then(|| constraint.clone());
+        if self.store_mut(store).add(constraint) {
+            if let Some(constraint) = entry {
+                self.trail.push(TrailEntry::Added(store, constraint));
+            }
+        }
+    }
+    fn remove_from(&mut self, store: Store, constraint: Constraint<T, C, W>) -> bool {
+        let entry = self.is_recording().then(|| constraint.clone());
+        if !self.store_mut(store).remove(constraint) {
+            return false;
+        }
+        self.network_stale |= matches!(store, Store::Feasible);
+        if let Some(constraint) = entry {
+            self.trail.push(TrailEntry::Removed(store, constraint));
+        }
+        true
+    }
+    fn new_id(&mut self) -> ConstraintId {
+        self.next_id += 1;
+        ConstraintId(self.next_id - 1)
+    }
+    fn register(&mut self, id: ConstraintId, constraint: Constraint<T, C, W>) {
+        self.ids.entry(constraint.clone()).or_default().push(id);
+        self.handles.insert(id, constraint);
+        if self.is_recording() {
+            self.trail.push(TrailEntry::Registered(id));
+        }
+    }
+    fn unregister(&mut self, id: ConstraintId) -> Option<Constraint<T, C, W>> {
+        let constraint = self.handles.remove(&id)?;
+        self.remove_id(&constraint, id);
+        if self.is_recording() {
+            self.trail
+                .push(TrailEntry::Unregistered(id, constraint.clone()));
+        }
+        Some(constraint)
+    }
+    fn remove_id(&mut self, constraint: &Constraint<T, C, W>, id: ConstraintId) {
+        let ids = self.ids.get_mut(constraint).unwrap();
+        ids.retain(|other| *other != id);
+        if ids.is_empty() {
+            self.ids.remove(constraint);
+        }
+    }
+    pub fn get(&self, id: ConstraintId) -> Option<&Constraint<T, C, W>> {
+        self.handles.get(&id)
+    }
+    pub fn constraints(&self) -> impl Iterator<Item = (ConstraintId, &Constraint<T, C, W>)> + '_ {
+        // the halves of intervals are left out, see `intervals`.
+        self.handles
+            .iter()
+            .filter(|(id, _)| !self.intervals.contains_key(id))
+            .map(|(id, constraint)| (*id, constraint))
+    }
+    fn assign(&mut self, changes: Solution<T, W>, sol: &mut Solution<T, W>) {
+        for (var, val) in changes.0 {
+            if self.is_recording() {
+                self.trail
+                    .push(TrailEntry::Assigned(var.clone(), sol.get(&var).cloned()));
+            }
+            sol.update(&var, val);
+        }
+    }
+    fn add_to_feasible(&mut self, constraint: Constraint<T, C, W>) {
+        self.add_to(Store::Feasible, constraint);
+    }
+    fn accept(
+        &mut self,
+        constraint: Constraint<T, C, W>,
+        changes: Solution<T, W>,
+        sol: &mut Solution<T, W>,
+    ) -> DcsResult<(), T, C, W> {
+        // moves a constraint that was checked against sol into the feasible set.
+        if !self.watched_constraints.is_empty() {
+            self.propagate_watches(&constraint, sol);
+        }
+        let weight = constraint.weight().get()?;
+        self.assign(changes, sol);
+        if let (Some(network), false) = (self.network.as_mut(), self.network_stale) {
+            self.network_stale = network
+                .add_edge(&constraint.u, &constraint.v, weight)
+                .is_none();
+        }
+        self.add_to_feasible(constraint);
+        Ok(())
+    }
+    pub fn maintain_network(&mut self, sol: &Solution<T, W>) -> DcsResult<(), T, C, W> {
+        // from now on the minimal network is kept up to date, and get_implied_ub is a lookup.
+        self.network = Some(self.minimal_network(sol)?);
+        self.network_stale = false;
+        Ok(())
+    }
+    pub fn drop_network(&mut self) {
+        self.network = None;
+        self.network_stale = false;
+    }
+    pub fn network(&self) -> Option<&MinimalNetwork<T, W>> {
+        // None if the network is not maintained, or if its bounds overflowed. an overflow on addition is not
+        // retried, so the network is back only after the next removal, relaxation or pop.
+        self.network.as_ref().filter(|_| !self.network_stale)
+    }
+    fn refresh_network(&mut self, sol: &Solution<T, W>) {
+        if self.network.is_none() || !self.network_stale {
+            return;
+        }
+        if let Ok(network) = self.minimal_network(sol) {
+            self.network = Some(network);
+            self.network_stale = false;
+        }
+    }
+    pub fn watch(
+        &mut self,
+        constraint: Constraint<T, C, W>,
+        sol: &Solution<T, W>,
+    ) -> DcsResult<(), T, C, W> {
+        // the event is reported right away if the constraint is already implied or impossible.
+        constraint.check_strict()?;
+        let tolerance = Cost::from(self.tolerance);
+        let weight = Cost::from(constraint.weight().get()?);
+        let implied = self
+            .dist(&constraint.u, &constraint.v, sol)?
+            .is_some_and(|d| Cost::from(d) <= weight + tolerance);
+        let impossible = match self.dist(&constraint.v, &constraint.u, sol)? {
+            Some(d) => (Cost::from(d) + weight + tolerance).get()? < W::zero(),
+            None => false,
+        };
+        if implied {
+            self.watch_events.push(WatchEvent::Implied(constraint));
+        } else if impossible {
+            self.watch_events.push(WatchEvent::Impossible(constraint));
+        } else {
+            self.add_to(Store::Watched, constraint);
+        }
+        Ok(())
+    }
+    pub fn unwatch(&mut self, constraint: Constraint<T, C, W>) -> bool {
+        self.remove_from(Store::Watched, constraint)
+    }
+    pub fn all_watched_constraints(&self) -> impl Iterator<Item = Constraint<T, C, W>> + '_ {
+        self.watched_constraints.to_all_constraints()
+    }
+    pub fn take_watch_events(&mut self) -> Vec<WatchEvent<T, C, W>> {
+        std::mem::take(&mut self.watch_events)
+    }
+    fn propagate_watches(&mut self, constraint: &Constraint<T, C, W>, sol: &Solution<T, W>) {
+        // every path that is new after adding u -> v goes y ~> u -> v ~> x, so it is enough to know
+        // the distances into u and out of v, before the constraint is added.
+        // paths are compared as costs, so one that overflows is neither implied nor impossible, and one that
+        // underflows is both, without holding up the constraint that is being added.
+        let to_u = self.costs_to(&constraint.u, sol);
+        let from_v = self.costs_from(&constraint.v, sol);
+        let tolerance = Cost::from(self.tolerance);
+        let mut events = vec![];
+        for (y, y2u) in to_u.iter() {
+            // watched x - y <= k is implied if y ~> u -> v ~> x is at least as tight.
+            let y2v = *y2u + constraint.weight();
+            if let Some(from_y) = self.watched_constraints.0.get(y) {
+                for (x, k, edge) in from_y.to_all_pairs() {
+                    let Some(v2x) = from_v.get(x) else {
+                        continue;
+                    };
+                    if y2v + *v2x <= k + tolerance {
+                        events.push(WatchEvent::Implied(edge.to_constraint(x, y)));
+                    }
+                }
+            }
+            // watched y - x <= k is impossible if y ~> u -> v ~> x closes a negative cycle with it.
+            for x in self.watched_constraints.1.get(y).into_iter().flatten() {
+                let Some(v2x) = from_v.get(x) else {
+                    continue;
+                };
+                for (k, edge) in self.watched_constraints.0[x].get_all(y) {
+                    if y2v + *v2x + k + tolerance < Cost::zero() {
+                        events.push(WatchEvent::Impossible(edge.to_constraint(y, x)));
+                    }
+                }
+            }
+        }
+        for event in events {
+            let (WatchEvent::Implied(watched) | WatchEvent::Impossible(watched)) = &event;
+            if self.remove_from(Store::Watched, watched.clone()) {
+                self.watch_events.push(event);
+            }
+        }
+    }
+    fn add_to_infeasible(&mut self, constraint: Constraint<T, C, W>) {
+        self.add_to(Store::Infeasible, constraint);
+    }
+    pub fn add(&mut self, constraint: Constraint<T, C, W>) -> ConstraintId {
+        // defers the feasibility check to the next call to `solve`.
+        // an identical constraint that was already checked keeps its status.
+        let id = self.new_id();
+        if !self.ids.contains_key(&constraint) {
+            self.add_to(Store::Undetermined, constraint.clone());
+        }
+        self.register(id, constraint);
+        id
+    }
+    pub fn solve(&mut self, sol: &mut Solution<T, W>) -> Status {
+        // once a constraint is known to be infeasible, checking the undetermined ones is wasted work,
+        // since they can only end up infeasible as well. they are left undetermined until a removal.
+        if !self.infeasible_constraints.is_empty() {
+            return Status::Infeasible;
+        }
+        for constraint in self
+            .all_undetermined_constraints()
+            .collect::<Vec<Constraint<T, C, W>>>()
+        {
+            self.remove_from(Store::Undetermined, constraint.clone());
+            if let Err(DcsError::Infeasible(_)) = self.decide(constraint, sol) {
+                return Status::Infeasible;
+            }
+        }
+        // constraints that can't be checked without overflow stay undetermined.
+        self.status()
     }
     pub fn add_constraint(
         &mut self,
-        constraint: Constraint<T, C>,
-        sol: &Solution<T>,
-    ) -> Option<Solution<T>> {
-        let new_sol = self.check_and_solve_new_constraint(&constraint, sol);
-        match new_sol {
-            Some(_) => self.add_to_feasible(constraint),
-            None => self.add_to_infeasible(constraint),
+        constraint: Constraint<T, C, W>,
+        sol: &Solution<T, W>,
+    ) -> (ConstraintId, DcsResult<Solution<T, W>, T, C, W>) {
+        // the constraint is kept (and the handle stays valid) even if it is infeasible.
+        let id = self.new_id();
+        let result = self.insert_constraint(id, constraint, sol);
+        (id, result)
+    }
+    pub fn add_constraint_in_place(
+        &mut self,
+        constraint: Constraint<T, C, W>,
+        sol: &mut Solution<T, W>,
+    ) -> (ConstraintId, DcsResult<SolutionDelta<T, W>, T, C, W>) {
+        // like add_constraint, but updates sol instead of copying it. sol is left as it was on error.
+        let id = self.new_id();
+        let (result, delta) =
+            self.tracked(sol, |sys, sol| sys.insert_in_place(id, constraint, sol));
+        (id, result.map(|()| delta))
+    }
+    pub fn add_interval(
+        &mut self,
+        v: T,
+        u: T,
+        lo: W,
+        hi: W,
+        tag: C,
+        sol: &Solution<T, W>,
+    ) -> DcsResult<(IntervalId, Solution<T, W>), T, C, W> {
+        let mut new_sol = sol.clone();
+        let (id, _) = self.add_interval_in_place(v, u, lo, hi, tag, &mut new_sol)?;
+        Ok((id, new_sol))
+    }
+    pub fn add_interval_in_place(
+        &mut self,
+        v: T,
+        u: T,
+        lo: W,
+        hi: W,
+        tag: C,
+        sol: &mut Solution<T, W>,
+    ) -> DcsResult<(IntervalId, SolutionDelta<T, W>), T, C, W> {
+        // lo <= v - u <= hi. unlike add_constraint, nothing is kept if either half can't be added.
+        // the interval is stored as the halves v - u <= hi and u - v <= -lo. they don't show up as constraints
+        // of their own, and a negative cycle lists the interval among its elements.
+        let upper = Constraint::new(v.clone(), u.clone(), hi, tag.clone());
+        let lower = Constraint::new(
+            u,
+            v,
+            W::zero().checked_sub(&lo).ok_or(DcsError::Overflow)?,
+            tag,
+        );
+        let id = IntervalId(self.new_id(), self.new_id());
+        self.intervals.insert(id.0, id);
+        self.intervals.insert(id.1, id);
+        let result = self.transaction(sol, false, |sys, sol| {
+            sys.insert_in_place(id.0, upper, sol)?;
+            sys.insert_in_place(id.1, lower, sol)
+        });
+        if result.is_err() {
+            self.intervals.remove(&id.0);
+            self.intervals.remove(&id.1);
         }
-        new_sol
+        Ok((id, result?))
+    }
+    pub fn add_equality(
+        &mut self,
+        v: T,
+        u: T,
+        k: W,
+        tag: C,
+        sol: &Solution<T, W>,
+    ) -> DcsResult<(IntervalId, Solution<T, W>), T, C, W> {
+        // v - u == k
+        self.add_interval(v, u, k, k, tag, sol)
+    }
+    pub fn intervals(&self) -> impl Iterator<Item = IntervalId> + '_ {
+        self.handles
+            .keys()
+            .filter_map(|id| self.intervals.get(id).filter(|interval| interval.0 == *id))
+            .cloned()
+    }
+    pub fn get_interval(&self, id: IntervalId) -> Option<(W, W)> {
+        // (lo, hi)
+        let (upper, lower) = (self.get(id.0)?, self.get(id.1)?);
+        Some((W::zero().checked_sub(&lower.c)?, upper.c))
+    }
+    pub fn remove_interval(
+        &mut self,
+        id: IntervalId,
+        sol: &Solution<T, W>,
+    ) -> DcsResult<Solution<T, W>, T, C, W> {
+        let mut new_sol = sol.clone();
+        self.remove_interval_in_place(id, &mut new_sol)?;
+        Ok(new_sol)
+    }
+    pub fn remove_interval_in_place(
+        &mut self,
+        id: IntervalId,
+        sol: &mut Solution<T, W>,
+    ) -> DcsResult<SolutionDelta<T, W>, T, C, W> {
+        // both halves are removed, or neither.
+        for half in [id.0, id.1] {
+            if self.get(half).is_none() {
+                return Err(DcsError::UnknownConstraintId(half));
+            }
+        }
+        let (result, delta) = self.tracked(sol, |sys, sol| {
+            sys.remove_handle(id.0, sol)?;
+            sys.remove_handle(id.1, sol)
+        });
+        if !self.is_recording() {
+            // nothing can bring the halves back.
+            self.intervals.remove(&id.0);
+            self.intervals.remove(&id.1);
+        }
+        self.refresh_network(sol);
+        result.map(|()| delta)
+    }
+    fn insert_constraint(
+        &mut self,
+        id: ConstraintId,
+        constraint: Constraint<T, C, W>,
+        sol: &Solution<T, W>,
+    ) -> DcsResult<Solution<T, W>, T, C, W> {
+        let mut new_sol = sol.clone();
+        self.insert_in_place(id, constraint, &mut new_sol)?;
+        Ok(new_sol)
+    }
+    fn insert_in_place(
+        &mut self,
+        id: ConstraintId,
+        constraint: Constraint<T, C, W>,
+        sol: &mut Solution<T, W>,
+    ) -> DcsResult<(), T, C, W> {
+        self.register(id, constraint.clone());
+        // an identical constraint that was added with `add` is decided now as well.
+        self.remove_from(Store::Undetermined, constraint.clone());
+        self.decide(constraint, sol)
+    }
+    fn decide(
+        &mut self,
+        constraint: Constraint<T, C, W>,
+        sol: &mut Solution<T, W>,
+    ) -> DcsResult<(), T, C, W> {
+        let changes = self.solve_new_constraint(&constraint, sol);
+        self.settle(constraint, changes, sol)
+    }
+    fn settle(
+        &mut self,
+        constraint: Constraint<T, C, W>,
+        changes: DcsResult<Solution<T, W>, T, C, W>,
+        sol: &mut Solution<T, W>,
+    ) -> DcsResult<(), T, C, W> {
+        // puts a constraint in the store that matches the result of checking it against sol.
+        // a constraint that can't be checked without overflow is left undetermined.
+        let result = changes.and_then(|changes| self.accept(constraint.clone(), changes, sol));
+        match result {
+            Ok(()) => {}
+            Err(DcsError::Infeasible(_)) => self.add_to_infeasible(constraint),
+            Err(_) => self.add_to(Store::Undetermined, constraint),
+        }
+        result
     }
     pub fn check_and_solve_new_constraint(
         &self,
-        constraint: &Constraint<T, C>,
-        sol: &Solution<T>,
-    ) -> Option<Solution<T>> {
+        constraint: &Constraint<T, C, W>,
+        sol: &Solution<T, W>,
+    ) -> DcsResult<SolutionDelta<T, W>, T, C, W> {
+        // the changes that adding the constraint would make to sol, without adding it.
+        let changes = self.solve_new_constraint(constraint, sol)?;
+        Ok(SolutionDelta(
+            changes
+                .0
+                .into_iter()
+                .map(|(var, new)| {
+                    let old = sol.get(&var).cloned();
+                    (var, old, new)
+                })
+                .collect(),
+        ))
+    }
+    fn solve_new_constraint(
+        &self,
+        constraint: &Constraint<T, C, W>,
+        sol: &Solution<T, W>,
+    ) -> DcsResult<Solution<T, W>, T, C, W> {
+        // returns only the values that have to change.
+        constraint.check_strict()?;
         let mut new_sol = Solution::new();
-        let mut q: PriorityQueue<&T, (Reverse<i64>, i64)> = PriorityQueue::new();
+        let mut q: PriorityQueue<&T, (Reverse<Cost<W>>, W)> = PriorityQueue::new();
         let mut visited = HashSet::new();
-        let d_u = sol.get_or(&constraint.u, 0);
-        let d_v = sol.get_or(&constraint.v, 0);
-        q.push(&constraint.v, (Reverse(0), d_v));
+        // the edge (and its source) through which each node was last improved.
+        let mut parents: HashMap<&T, (&T, &Edge<C, W>)> = HashMap::new();
+        let d_u = Cost::from(sol.get_or(&constraint.u, W::zero()));
+        let d_v = sol.get_or(&constraint.v, W::zero());
+        q.push(&constraint.v, (Reverse(Cost::zero()), d_v));
         while let Some((x, (v2x_scaled, d_x))) = q.pop() {
             visited.insert(x);
-            let v2x_descaled = v2x_scaled.0 - d_v + d_x;
-            let new_val = d_u + constraint.c + v2x_descaled;
-            let is_affected = d_x > new_val;
+            let v2x_descaled = v2x_scaled.0 - Cost::from(d_v) + Cost::from(d_x);
+            // a value that overflows is above d_x, so x is left alone. only one that underflows can't be stored.
+            let new_val = d_u + constraint.weight() + v2x_descaled;
+            let is_affected = Cost::from(d_x) > new_val + Cost::from(self.tolerance);
             if !is_affected {
                 continue;
             }
             if x == &constraint.u {
-                return None;
+                let cycle = Self::negative_cycle(constraint, &parents)?;
+                return Err(DcsError::Infeasible(self.with_intervals(cycle)));
             }
-            new_sol.update(x, new_val);
+            new_sol.update(x, new_val.get()?);
             let Some(succesors) = self.feasible_constraints.0.get(x) else {
                     continue;
             };
             // equivalent to `for (y, x2y_scaled) in self.scaled_succesors(y, sol)`, but with less lookups.
-            for (y, x2y_unscaled, _) in succesors.to_pairs() {
-                let d_y = sol.get_or(y, 0);
-                let x2y_scaled = x2y_unscaled + d_x - d_y;
+            for (y, x2y_unscaled, edge) in succesors.to_pairs() {
+                let d_y = sol.get_or(y, W::zero());
+                // reduced costs are non-negative up to the tolerance, and dijkstra needs them non-negative.
+                let x2y_scaled =
+                    (x2y_unscaled + Cost::from(d_x) - Cost::from(d_y)).max(Cost::zero());
                 let v2y_scaled = v2x_scaled.0 + x2y_scaled;
-                if !visited.contains(y) {
-                    q.push_increase(y, (Reverse(v2y_scaled), d_y));
+                let priority = (Reverse(v2y_scaled), d_y);
+                if !visited.contains(y) && q.get_priority(y).is_none_or(|p| &priority > p) {
+                    q.push(y, priority);
+                    parents.insert(y, (x, edge));
                 }
             }
         }
-        new_sol.merge(sol);
-        Some(new_sol)
+        // variables are given a value when they first show up, so that the solution covers all of them.
+        for var in [&constraint.u, &constraint.v] {
+            if sol.get(var).is_none() && new_sol.get(var).is_none() {
+                new_sol.update(var, W::zero());
+            }
+        }
+        Ok(new_sol)
+    }
+    fn with_intervals(&self, mut cycle: NegativeCycle<T, C, W>) -> NegativeCycle<T, C, W> {
+        // a constraint held by several handles is reported as the latest of them.
+        let mut seen = HashSet::new();
+        cycle.elements = vec![];
+        for constraint in cycle.constraints.iter() {
+            let interval = self
+                .find_id(constraint)
+                .and_then(|id| self.intervals.get(&id));
+            let Some((id, upper, (lo, hi))) =
+                interval.and_then(|&id| Some((id, self.get(id.0)?, self.get_interval(id)?)))
+            else {
+                cycle
+                    .elements
+                    .push(CycleElement::Constraint(constraint.clone()));
+                continue;
+            };
+            if seen.insert(id) {
+                cycle.elements.push(CycleElement::Interval {
+                    id,
+                    v: upper.v.clone(),
+                    u: upper.u.clone(),
+                    lo,
+                    hi,
+                    tag: upper.tag.clone(),
+                });
+            }
+        }
+        cycle
+    }
+    fn negative_cycle(
+        constraint: &Constraint<T, C, W>,
+        parents: &HashMap<&T, (&T, &Edge<C, W>)>,
+    ) -> DcsResult<NegativeCycle<T, C, W>, T, C, W> {
+        // walk back from u to v along the search tree, then close the cycle with the new constraint.
+        let mut constraints = vec![];
+        let mut node = &constraint.u;
+        while node != &constraint.v {
+            let (parent, edge) = parents[node];
+            constraints.push(edge.to_constraint(node, parent));
+            node = parent;
+        }
+        constraints.push(constraint.clone());
+        constraints.reverse();
+        NegativeCycle::from_constraints(constraints)
     }
     pub fn remove_constraint(
         &mut self,
-        constraint_to_remove: Constraint<T, C>,
-        sol: &Solution<T>,
-    ) -> Solution<T> {
-        // todo: there are two types of remove with different tradeoffs.
-        // one (implemented below) that after removing an infeasible constraints, does not need to check the other feasible constraints.
-        // however it does need to check every new constraint, even if the system is infeasible already.
-        // the other doesn't need check new constraints when the system is already infeasible, but need to check every infeasible constraint after removal.
-        // idea: hold 3 sets of constraints: feasible, infeasible, undetermined.
-        // when adding a constraint, always add to undetermined.
-        // self.status() -> Status { if self.infeasible.not_empty => InFeasible, else: {if self.undetermined.is_empty -> Feasible, Unfeasible}}
-        // solve makes an undetermined system determined.
-        // if the infeasible set is empty, it tries to empty the underetmined set.
-        // addind constraints always adds them to the underetmined set.
-        // removing a constraint: if undetermined, simply remove.
-        // otherwise, move all infeasible constraints to undetermined.
-        let mut new_sol = sol.clone(); // todo: try not to clone. maybe just consume sol (or mut it)
+        constraint_to_remove: Constraint<T, C, W>,
+        sol: &Solution<T, W>,
+    ) -> DcsResult<Solution<T, W>, T, C, W> {
+        let mut new_sol = sol.clone();
+        self.remove_constraint_in_place(constraint_to_remove, &mut new_sol)?;
+        Ok(new_sol)
+    }
+    pub fn remove_constraint_in_place(
+        &mut self,
+        constraint_to_remove: Constraint<T, C, W>,
+        sol: &mut Solution<T, W>,
+    ) -> DcsResult<SolutionDelta<T, W>, T, C, W> {
+        // the halves of an interval can only be removed with it.
+        let id = self.ids.get(&constraint_to_remove).and_then(|ids| {
+            ids.iter()
+                .rev()
+                .find(|id| !self.intervals.contains_key(id))
+                .cloned()
+        });
+        let Some(id) = id else {
+            return Err(DcsError::UnknownConstraint(constraint_to_remove));
+        };
+        self.remove_by_id_in_place(id, sol)
+    }
+    fn find_id(&self, constraint: &Constraint<T, C, W>) -> Option<ConstraintId> {
+        self.ids.get(constraint).and_then(|ids| ids.last().cloned())
+    }
+    pub fn remove_by_id(
+        &mut self,
+        id: ConstraintId,
+        sol: &Solution<T, W>,
+    ) -> DcsResult<Solution<T, W>, T, C, W> {
+        let mut new_sol = sol.clone();
+        self.remove_by_id_in_place(id, &mut new_sol)?;
+        Ok(new_sol)
+    }
+    pub fn remove_by_id_in_place(
+        &mut self,
+        id: ConstraintId,
+        sol: &mut Solution<T, W>,
+    ) -> DcsResult<SolutionDelta<T, W>, T, C, W> {
+        if self.intervals.contains_key(&id) {
+            // a half of an interval, see remove_interval.
+            return Err(DcsError::UnknownConstraintId(id));
+        }
+        let (result, delta) = self.tracked(sol, |sys, sol| sys.remove_handle(id, sol));
+        self.refresh_network(sol);
+        result.map(|()| delta)
+    }
+    fn remove_handle(
+        &mut self,
+        id: ConstraintId,
+        sol: &mut Solution<T, W>,
+    ) -> DcsResult<(), T, C, W> {
+        let Some(constraint) = self.unregister(id) else {
+            return Err(DcsError::UnknownConstraintId(id));
+        };
+        if self.ids.contains_key(&constraint) {
+            // another handle still holds an identical constraint.
+            return Ok(());
+        }
+        self.remove_from_stores(constraint, sol);
+        Ok(())
+    }
+    pub fn update_bound(
+        &mut self,
+        id: ConstraintId,
+        new_c: W,
+        sol: &mut Solution<T, W>,
+    ) -> DcsResult<SolutionDelta<T, W>, T, C, W> {
+        // the handle stays valid, and now refers to the constraint with the new bound.
+        // like add_constraint, an infeasible bound is kept, and the error carries the cycle. sol is left as it was,
+        // unless dropping the old bound let some other infeasible constraint in.
+        // on any other error nothing changes, and the handle keeps its old bound.
+        let Some(constraint) = self.get(id) else {
+            return Err(DcsError::UnknownConstraintId(id));
+        };
+        if new_c <= constraint.c {
+            self.tighten(id, new_c, sol)
+        } else {
+            self.relax(id, new_c, sol)
+        }
+    }
+    pub fn tighten(
+        &mut self,
+        id: ConstraintId,
+        new_c: W,
+        sol: &mut Solution<T, W>,
+    ) -> DcsResult<SolutionDelta<T, W>, T, C, W> {
+        let result = self.transaction(sol, true, |sys, sol| sys.tighten_handle(id, new_c, sol));
+        self.refresh_network(sol);
+        result
+    }
+    pub fn relax(
+        &mut self,
+        id: ConstraintId,
+        new_c: W,
+        sol: &mut Solution<T, W>,
+    ) -> DcsResult<SolutionDelta<T, W>, T, C, W> {
+        let result = self.transaction(sol, true, |sys, sol| sys.relax_handle(id, new_c, sol));
+        self.refresh_network(sol);
+        result
+    }
+    fn tighten_handle(
+        &mut self,
+        id: ConstraintId,
+        new_c: W,
+        sol: &mut Solution<T, W>,
+    ) -> DcsResult<(), T, C, W> {
+        let Some(constraint) = self.get(id).cloned() else {
+            return Err(DcsError::UnknownConstraintId(id));
+        };
+        if new_c > constraint.c {
+            return self.relax_handle(id, new_c, sol);
+        }
+        let tightened = Constraint {
+            c: new_c,
+            ..constraint.clone()
+        };
+        if self.is_shared(&constraint, &tightened) {
+            return self.reinsert(id, new_c, sol);
+        }
+        self.unregister(id);
+        self.register(id, tightened.clone());
+        if self.remove_from(Store::Undetermined, constraint.clone()) {
+            self.add_to(Store::Undetermined, tightened);
+            return Ok(());
+        }
+        // bounds only get tighter, so the network can take the new edge in place of the old one.
+        let was_stale = self.network_stale;
+        if !self.remove_from_feasible(constraint.clone()) {
+            self.remove_from_infeasible(constraint);
+            return self.decide(tightened, sol);
+        }
+        self.network_stale = was_stale;
+        // the old edge is dropped, so the search is the same as for a new constraint.
+        let result = self.decide(tightened.clone(), sol);
+        if let Err(DcsError::Infeasible(_)) = result {
+            // the old edge is gone and the new one isn't there, so the network has to be recomputed.
+            // without the old edge some infeasible constraints may be feasible now.
+            self.network_stale = true;
+            self.recheck_infeasible(&tightened.u, &tightened.v, sol);
+        }
+        result
+    }
+    fn relax_handle(
+        &mut self,
+        id: ConstraintId,
+        new_c: W,
+        sol: &mut Solution<T, W>,
+    ) -> DcsResult<(), T, C, W> {
+        let Some(constraint) = self.get(id).cloned() else {
+            return Err(DcsError::UnknownConstraintId(id));
+        };
+        if new_c < constraint.c {
+            return self.tighten_handle(id, new_c, sol);
+        }
+        let relaxed = Constraint {
+            c: new_c,
+            ..constraint.clone()
+        };
+        if self.is_shared(&constraint, &relaxed) {
+            return self.reinsert(id, new_c, sol);
+        }
+        self.unregister(id);
+        self.register(id, relaxed.clone());
+        if self.remove_from_feasible(constraint.clone()) {
+            // sol satisfies the relaxed constraint as well.
+            self.add_to_feasible(relaxed.clone());
+            self.recheck_infeasible(&relaxed.u, &relaxed.v, sol);
+            return Ok(());
+        }
+        if self.remove_from(Store::Undetermined, constraint.clone()) {
+            self.add_to(Store::Undetermined, relaxed);
+            return Ok(());
+        }
+        self.remove_from_infeasible(constraint);
+        self.decide(relaxed, sol)
+    }
+    fn is_shared(&self, old: &Constraint<T, C, W>, new: &Constraint<T, C, W>) -> bool {
+        // whether another handle holds the same edge as the handle, either before or after its bound changes.
+        self.ids[old].len() > 1 || (new != old && self.ids.contains_key(new))
+    }
+    fn reinsert(
+        &mut self,
+        id: ConstraintId,
+        new_c: W,
+        sol: &mut Solution<T, W>,
+    ) -> DcsResult<(), T, C, W> {
+        // used when the edge is shared with another handle, and can't be changed in place.
+        // the new constraint goes through the same path as an added one, which merges it with an identical one.
+        let constraint = self.get(id).cloned().unwrap();
+        self.remove_handle(id, sol)?;
+        self.insert_in_place(
+            id,
+            Constraint {
+                c: new_c,
+                ..constraint
+            },
+            sol,
+        )
+    }
+    fn remove_from_stores(
+        &mut self,
+        constraint_to_remove: Constraint<T, C, W>,
+        sol: &mut Solution<T, W>,
+    ) {
+        // undetermined and infeasible constraints are simply dropped.
+        // dropping a feasible constraint may resolve conflicts, so the infeasible constraints are re-checked
+        // right away. undetermined constraints are left for the next `solve`.
+        // the network is left stale, and refreshed by the caller once the whole operation is done.
+        if self.remove_from(Store::Undetermined, constraint_to_remove.clone()) {
+            return;
+        }
         if self.remove_from_infeasible(constraint_to_remove.clone()) {
-            return new_sol;
+            return;
         }
+        let (u, v) = (
+            constraint_to_remove.u.clone(),
+            constraint_to_remove.v.clone(),
+        );
         if !self.remove_from_feasible(constraint_to_remove) {
-            return new_sol;
+            return;
+        }
+        self.recheck_infeasible(&u, &v, sol);
+    }
+    fn recheck_infeasible(&mut self, u: &T, v: &T, sol: &mut Solution<T, W>) {
+        // after the edge u -> v was removed or relaxed, an infeasible constraint can become feasible only
+        // if its cycle could pass through that edge, i.e. if it goes from a node reachable from v
+        // to a node that reaches u.
+        if self.infeasible_constraints.is_empty() {
+            return;
         }
         // todo: not a great implemenataion. wroking with constraint objects below seems redundant
-        for constraint in self
-            .all_infeasible_constraints()
-            .collect::<Vec<Constraint<T, C>>>()
-        {
-            if let Some(new_sol2) = self.check_and_solve_new_constraint(&constraint, &new_sol) {
-                new_sol = new_sol2;
-                self.remove_from_infeasible(constraint.clone());
-                self.add_to_feasible(constraint);
+        let candidates: Vec<Constraint<T, C, W>> =
+            match (self.distances_to(u, sol), self.distances_from(v, sol)) {
+                (Ok(to_u), Ok(from_v)) => self
+                    .infeasible_constraints
+                    .to_all_constraints()
+                    .filter(|constraint| {
+                        to_u.contains_key(&constraint.v) && from_v.contains_key(&constraint.u)
+                    })
+                    .collect(),
+                // without the distances, every infeasible constraint is a candidate.
+                _ => self.infeasible_constraints.to_all_constraints().collect(),
+            };
+        for constraint in candidates {
+            let changes = self.solve_new_constraint(&constraint, sol);
+            if let Err(DcsError::Infeasible(_)) = changes {
+                continue;
             }
+            self.remove_from_infeasible(constraint.clone());
+            let _ = self.settle(constraint, changes, sol);
         }
-        new_sol
     }
-    pub fn remove_constraints<I: Iterator<Item = Constraint<T, C>>>(
+    pub fn remove_constraints<I: Iterator<Item = Constraint<T, C, W>>>(
         &mut self,
         constraints: I,
-        sol: &Solution<T>,
-    ) -> Solution<T> {
-        let mut new_sol = sol.clone(); // todo: avoid this clone. maybe just consume sol (or mut it)
-        for constraint in constraints {
-            new_sol = self.remove_constraint(constraint, &new_sol);
+        sol: &Solution<T, W>,
+    ) -> DcsResult<Solution<T, W>, T, C, W> {
+        let mut new_sol = sol.clone();
+        self.remove_constraints_in_place(constraints, &mut new_sol)?;
+        Ok(new_sol)
+    }
+    pub fn remove_constraints_in_place<I: Iterator<Item = Constraint<T, C, W>>>(
+        &mut self,
+        constraints: I,
+        sol: &mut Solution<T, W>,
+    ) -> DcsResult<SolutionDelta<T, W>, T, C, W> {
+        // nothing is removed if any of the constraints is unknown.
+        let constraints: Vec<Constraint<T, C, W>> = constraints.collect();
+        let mut counts: HashMap<&Constraint<T, C, W>, usize> = HashMap::new();
+        for constraint in constraints.iter() {
+            let count = counts.entry(constraint).or_default();
+            *count += 1;
+            if self.ids.get(constraint).map_or(0, |ids| ids.len()) < *count {
+                return Err(DcsError::UnknownConstraint(constraint.clone()));
+            }
         }
-        new_sol
+        let (result, delta) = self.tracked(sol, |sys, sol| {
+            for constraint in constraints {
+                let id = sys.find_id(&constraint).unwrap();
+                sys.remove_handle(id, sol)?;
+            }
+            Ok(())
+        });
+        // the network is recomputed once for the whole batch.
+        self.refresh_network(sol);
+        result.map(|()| delta)
     }
-    fn remove_from_infeasible(&mut self, constraint: Constraint<T, C>) -> bool {
-        self.infeasible_constraints.remove(constraint)
+    fn remove_from_infeasible(&mut self, constraint: Constraint<T, C, W>) -> bool {
+        self.remove_from(Store::Infeasible, constraint)
     }
-    fn remove_from_feasible(&mut self, constraint: Constraint<T, C>) -> bool {
-        self.feasible_constraints.remove(constraint)
+    fn remove_from_feasible(&mut self, constraint: Constraint<T, C, W>) -> bool {
+        self.remove_from(Store::Feasible, constraint)
     }
-    pub fn get_implied_ub(&self, x: &T, y: &T, sol: &Solution<T>) -> Option<i64> {
-        // gives the constraint x - y <= a (with smallest possible a) that is implied by the system
+    pub fn get_implied_ub(
+        &self,
+        x: &T,
+        y: &T,
+        sol: &Solution<T, W>,
+    ) -> DcsResult<Option<W>, T, C, W> {
+        // gives the constraint x - y <= a (with smallest possible a) that is implied by the system.
+        // Ok(None) means x - y is unbounded from above.
+        for var in [x, y] {
+            if !self.contains_var(var) {
+                return Err(DcsError::UnknownVariable(var.clone()));
+            }
+        }
+        if let Some(network) = self.network() {
+            if network.contains_var(x) && network.contains_var(y) {
+                return Ok(network.get_implied_ub(x, y));
+            }
+        }
         self.dist(y, x, sol)
     }
-    pub fn get_implied_lb(&self, x: &T, y: &T, sol: &Solution<T>) -> Option<i64> {
+    pub fn explain_implied_ub(
+        &self,
+        x: &T,
+        y: &T,
+        sol: &Solution<T, W>,
+    ) -> DcsResult<Option<Path<T, C, W>>, T, C, W> {
+        // the feasible constraints along a shortest path from y to x. their bounds add up to get_implied_ub(x, y)
+        // (with c - 1 or c - δ in place of c for strict ones). Ok(None) if x - y is unbounded from above.
+        for var in [x, y] {
+            if !self.contains_var(var) {
+                return Err(DcsError::UnknownVariable(var.clone()));
+            }
+        }
+        self.shortest_path(y, x, sol)
+    }
+    pub fn implied_ubs_from(
+        &self,
+        y: &T,
+        sol: &Solution<T, W>,
+    ) -> DcsResult<HashMap<T, W>, T, C, W> {
+        // the smallest a for which x - y <= a is implied, for every x for which there is one (including y itself).
+        if !self.contains_var(y) {
+            return Err(DcsError::UnknownVariable(y.clone()));
+        }
+        match self.network().filter(|network| network.contains_var(y)) {
+            Some(network) => Ok(network.implied_ubs_from(y)),
+            None => self.distances_from(y, sol),
+        }
+    }
+    pub fn implied_ubs_to(&self, x: &T, sol: &Solution<T, W>) -> DcsResult<HashMap<T, W>, T, C, W> {
+        // the smallest a for which x - y <= a is implied, for every y for which there is one (including x itself).
+        if !self.contains_var(x) {
+            return Err(DcsError::UnknownVariable(x.clone()));
+        }
+        match self.network().filter(|network| network.contains_var(x)) {
+            Some(network) => Ok(network.implied_ubs_to(x)),
+            None => self.distances_to(x, sol),
+        }
+    }
+    pub fn get_implied_lb(
+        &self,
+        x: &T,
+        y: &T,
+        sol: &Solution<T, W>,
+    ) -> DcsResult<Option<W>, T, C, W> {
         // gives the constraint x - y >= a (with larget possible a) that is implied by the system.
         // equivalent to y - x <= -a
-        self.get_implied_ub(x, y, sol).map(|ub| -ub)
+        self.get_implied_ub(x, y, sol)?
+            .map(|ub| (Cost::zero() - Cost::from(ub)).get())
+            .transpose()
     }
-    fn dist(&self, from_node: &T, to_node: &T, sol: &Solution<T>) -> Option<i64> {
+    fn contains_var(&self, var: &T) -> bool {
+        // a variable is known if any constraint in the system mentions it.
+        self.feasible_constraints.contains_var(var)
+            || self.infeasible_constraints.contains_var(var)
+            || self.undetermined_constraints.contains_var(var)
+    }
+    fn dist(
+        &self,
+        from_node: &T,
+        to_node: &T,
+        sol: &Solution<T, W>,
+    ) -> DcsResult<Option<W>, T, C, W> {
         let result = dijkstra(
             from_node,
             |node| self.scaled_succesors(node, sol),
             |node| node == to_node,
         );
-        result.map(|(_, cost)| self.descale_dist(cost, from_node, to_node, sol))
+        result
+            .map(|(_, cost)| self.descale_dist(cost, from_node, to_node, sol).get())
+            .transpose()
     }
-    fn scaled_succesors(&self, node: &T, sol: &Solution<T>) -> Vec<(T, i64)> {
+    fn distances_from(
+        &self,
+        from_node: &T,
+        sol: &Solution<T, W>,
+    ) -> DcsResult<HashMap<T, W>, T, C, W> {
+        // distances from from_node to every node it reaches, including itself.
+        self.costs_from(from_node, sol)
+            .into_iter()
+            .map(|(node, cost)| Ok((node, cost.get()?)))
+            .collect()
+    }
+    fn distances_to(&self, to_node: &T, sol: &Solution<T, W>) -> DcsResult<HashMap<T, W>, T, C, W> {
+        // distances to to_node from every node that reaches it, including itself.
+        self.costs_to(to_node, sol)
+            .into_iter()
+            .map(|(node, cost)| Ok((node, cost.get()?)))
+            .collect()
+    }
+    fn costs_from(&self, from_node: &T, sol: &Solution<T, W>) -> HashMap<T, Cost<W>> {
+        let mut out: HashMap<T, Cost<W>> =
+            dijkstra_all(from_node, |node| self.scaled_succesors(node, sol))
+                .into_iter()
+                .map(|(node, (_, cost))| {
+                    let cost = self.descale_dist(cost, from_node, &node, sol);
+                    (node, cost)
+                })
+                .collect();
+        out.insert(from_node.clone(), Cost::zero());
+        out
+    }
+    fn costs_to(&self, to_node: &T, sol: &Solution<T, W>) -> HashMap<T, Cost<W>> {
+        let mut out: HashMap<T, Cost<W>> =
+            dijkstra_all(to_node, |node| self.scaled_predecessors(node, sol))
+                .into_iter()
+                .map(|(node, (_, cost))| {
+                    let cost = self.descale_dist(cost, &node, to_node, sol);
+                    (node, cost)
+                })
+                .collect();
+        out.insert(to_node.clone(), Cost::zero());
+        out
+    }
+    fn scaled_predecessors(&self, node: &T, sol: &Solution<T, W>) -> Vec<(T, Cost<W>)> {
+        let d_node = Cost::from(sol.get_or(node, W::zero()));
+        self.feasible_constraints
+            .predecessors(node)
+            .map(|(x, w, _)| {
+                let scaled = Cost::from(sol.get_or(x, W::zero())) + w - d_node;
+                (x.clone(), scaled.max(Cost::zero()))
+            })
+            .collect()
+    }
+    pub(crate) fn shortest_path(
+        &self,
+        from_node: &T,
+        to_node: &T,
+        sol: &Solution<T, W>,
+    ) -> DcsResult<Option<Path<T, C, W>>, T, C, W> {
+        // the chain of feasible constraints along a shortest path from from_node to to_node.
+        let Some((nodes, cost)) = dijkstra(
+            from_node,
+            |node| self.scaled_succesors(node, sol),
+            |node| node == to_node,
+        ) else {
+            return Ok(None);
+        };
+        // a path that overflows is not necessarily the shortest one.
+        cost.get()?;
+        let path = nodes
+            .windows(2)
+            .map(|pair| {
+                let (_, edge) = self.feasible_constraints.0[&pair[0]].get(&pair[1]).unwrap();
+                edge.to_constraint(&pair[1], &pair[0])
+            })
+            .collect();
+        Ok(Some(path))
+    }
+    fn scaled_succesors(&self, node: &T, sol: &Solution<T, W>) -> Vec<(T, Cost<W>)> {
         // todo: return an iterator instead of Vec
         let Some(from_edges) = self.feasible_constraints.0.get(node) else {
             return vec![]
         };
-        let d_node = sol.get_or(node, 0);
+        let d_node = Cost::from(sol.get_or(node, W::zero()));
         let out = from_edges
             .to_pairs()
-            .map(|(y, w, _)| (y.clone(), d_node + w - sol.get_or(y, 0)))
+            .map(|(y, w, _)| {
+                (
+                    y.clone(),
+                    (d_node + w - Cost::from(sol.get_or(y, W::zero()))).max(Cost::zero()),
+                )
+            })
             .collect();
         out
     }
-    fn descale_dist(&self, scaled_dist: i64, from_node: &T, to_node: &T, sol: &Solution<T>) -> i64 {
-        -sol.get_or(from_node, 0) + scaled_dist + sol.get_or(to_node, 0)
+    fn descale_dist(
+        &self,
+        scaled_dist: Cost<W>,
+        from_node: &T,
+        to_node: &T,
+        sol: &Solution<T, W>,
+    ) -> Cost<W> {
+        scaled_dist - Cost::from(sol.get_or(from_node, W::zero()))
+            + Cost::from(sol.get_or(to_node, W::zero()))
     }
 }
 
-impl<T: VarId, C: ConstraintTag> Default for DCS<T, C> {
+impl<T: VarId, C: ConstraintTag, W: Weight> Default for DCS<T, C, W> {
     fn default() -> Self {
         Self::new()
     }
@@ -344,7 +2049,7 @@ mod tests {
     fn as_constraints<T: VarId, I: Iterator<Item = (T, T, i64)>>(
         tuples: I,
     ) -> impl Iterator<Item = Constraint<T, ()>> {
-        tuples.map(|(v, u, c)| Constraint { v, u, c, tag: () })
+        tuples.map(|(v, u, c)| Constraint::new(v, u, c, ()))
     }
     fn expect_feasible<T: VarId, C: ConstraintTag, It: Iterator<Item = Constraint<T, C>>>(
         constraints: It,
@@ -363,7 +2068,7 @@ mod tests {
         let mut sys = DCS::new();
         let mut sol = Solution::new();
         for constraint in constraints {
-            sol = sys.add_constraint(constraint, &sol).unwrap();
+            sol = sys.add_constraint(constraint, &sol).1.unwrap();
             assert!(sys.is_feasible());
             assert!(sys.check_solution(&sol));
         }
@@ -374,21 +2079,22 @@ mod tests {
         let vec = Vec::from_iter(constraints);
         let (sys, sol) = DCS::from_scratch(vec.clone().into_iter());
         println!("{:#?}", vec);
+        let mut sys2 = DCS::new();
+        let mut sol2 = Solution::new();
+        for constraint in vec {
+            match sys2.add_constraint(constraint, &sol2).1 {
+                Ok(new_sol) => sol2 = new_sol,
+                Err(DcsError::Infeasible(cycle)) => check_negative_cycle(&cycle, &sys2),
+                Err(err) => panic!("{}", err),
+            }
+        }
         assert!(!sys.is_feasible());
         assert!(sys.check_solution(&sol)); // todo: uncomment
         (sys, sol)
     }
     #[test]
-    fn test_single_constraint() {
-        expect_feasible(
-            [Constraint {
-                v: "x",
-                u: "y",
-                c: 0,
-                tag: (),
-            }]
-            .into_iter(),
-        );
+    fn test_single_constraint() {
+        expect_feasible([Constraint::new("x", "y", 0, ())].into_iter());
     }
 
     #[test]
@@ -416,12 +2122,7 @@ mod tests {
             x.insert(key, val_to_insert);
         }
         x.iter()
-            .map(|((v, u), (c, tag))| Constraint {
-                v: v.clone(),
-                u: u.clone(),
-                c: *c,
-                tag: tag.clone(),
-            })
+            .map(|((v, u), (c, tag))| Constraint::new(v.clone(), u.clone(), *c, tag.clone()))
             .collect()
     }
     #[test]
@@ -429,7 +2130,113 @@ mod tests {
         let (sys, sol) = DCS::from_scratch(as_constraints(
             [("y", "x", 1), ("z", "y", 2), ("x", "z", -3), ("z", "x", 4)].into_iter(),
         ));
-        assert_eq!(sys.get_implied_ub(&"z", &"x", &sol).unwrap(), 3);
+        assert_eq!(sys.get_implied_ub(&"z", &"x", &sol), Ok(Some(3)));
+    }
+
+    #[test]
+    fn test_predecessors_and_successors() {
+        let mut sys = DCS::new();
+        let mut sol = Solution::new();
+        let mut ids = vec![];
+        for (v, u, c, tag) in [
+            ("y", "x", 3, 0),
+            ("y", "x", 5, 1),
+            ("z", "x", 1, 2),
+            ("x", "z", -1, 3),
+            ("y", "z", 2, 4),
+            ("x", "y", -4, 5),
+        ] {
+            let (id, result) = sys.add_constraint(Constraint::new(v, u, c, tag), &sol);
+            ids.push(id);
+            if let Ok(new_sol) = result {
+                sol = new_sol;
+            }
+        }
+        let tags = |constraints: Vec<Constraint<&str, i32>>| {
+            let mut tags: Vec<i32> = constraints.into_iter().map(|c| c.tag).collect();
+            tags.sort();
+            tags
+        };
+        // the last constraint closes a negative cycle, so it is not part of the graph.
+        assert_eq!(tags(sys.successors(&"x").collect()), vec![0, 1, 2]);
+        assert_eq!(tags(sys.predecessors(&"y").collect()), vec![0, 1, 4]);
+        assert_eq!(tags(sys.predecessors(&"x").collect()), vec![3]);
+        assert!(sys
+            .predecessors(&"x")
+            .all(|c| c == Constraint::new("x", "z", -1, 3)));
+        assert_eq!(sys.successors(&"w").count(), 0);
+
+        sol = sys.remove_by_id(ids[0], &sol).unwrap();
+        sol = sys.remove_by_id(ids[4], &sol).unwrap();
+        // without tag 0, tag 5 is feasible.
+        assert_eq!(tags(sys.predecessors(&"y").collect()), vec![1]);
+        assert_eq!(tags(sys.predecessors(&"x").collect()), vec![3, 5]);
+        assert_eq!(tags(sys.successors(&"z").collect()), vec![3]);
+        assert!(sys.check_solution(&sol));
+    }
+
+    #[test]
+    fn test_explain_implied_ub() {
+        for seed in 0..10 {
+            let (constraints, _) = generate_random_feasible_constraints(8, 12, 2, seed);
+            let (sys, sol) = DCS::from_scratch(constraints.into_iter());
+            let vars: Vec<usize> = sol.variables().cloned().collect();
+            for x in vars.iter() {
+                for y in vars.iter() {
+                    let ub = sys.get_implied_ub(x, y, &sol).unwrap();
+                    let path = sys.explain_implied_ub(x, y, &sol).unwrap();
+                    let Some(path) = path else {
+                        assert_eq!(ub, None);
+                        continue;
+                    };
+                    assert_eq!(Some(path.iter().map(|c| c.c).sum()), ub);
+                    let mut node = y;
+                    for constraint in path.iter() {
+                        assert_eq!(&constraint.u, node);
+                        assert!(sys.successors(node).any(|c| &c == constraint));
+                        node = &constraint.v;
+                    }
+                    assert_eq!(node, x);
+                    if x == y {
+                        assert!(path.is_empty());
+                    }
+                }
+            }
+            assert_eq!(
+                sys.explain_implied_ub(&vars[0], &8, &sol),
+                Err(DcsError::UnknownVariable(8))
+            );
+        }
+    }
+
+    #[test]
+    fn test_implied_ubs_from_and_to() {
+        for seed in 0..10 {
+            let (constraints, _) = generate_random_feasible_constraints(8, 12, 0, seed);
+            let (mut sys, sol) = DCS::from_scratch(constraints.into_iter());
+            let vars: Vec<usize> = sol.variables().cloned().collect();
+            for maintained in [false, true] {
+                if maintained {
+                    sys.maintain_network(&sol).unwrap();
+                }
+                for y in vars.iter() {
+                    let from_y = sys.implied_ubs_from(y, &sol).unwrap();
+                    let to_y = sys.implied_ubs_to(y, &sol).unwrap();
+                    for x in vars.iter() {
+                        let (x2y, y2x) = (
+                            sys.get_implied_ub(x, y, &sol).unwrap(),
+                            sys.get_implied_ub(y, x, &sol).unwrap(),
+                        );
+                        assert_eq!(from_y.get(x).cloned(), x2y);
+                        assert_eq!(to_y.get(x).cloned(), y2x);
+                    }
+                }
+            }
+            assert_eq!(
+                sys.implied_ubs_from(&8, &sol),
+                Err(DcsError::UnknownVariable(8))
+            );
+        }
     }
 
     fn generate_random_feasible_constraints(
@@ -455,23 +2262,18 @@ mod tests {
             .iter()
             .choose_multiple(&mut rng, num_constraints)
             .into_iter()
-            .map(|(v, u)| Constraint {
-                v: *v,
-                u: *u,
-                c: x[*v] - x[*u],
-                tag: (),
-            })
+            .map(|(v, u)| Constraint::new(*v, *u, x[*v] - x[*u], ()))
             .collect();
         out.shuffle(&mut rng);
         for i in 0..num_dup_constraints {
             let constraint = out[i].clone();
             let extra_c = rng.gen_range(0..10);
-            out.push(Constraint {
-                v: constraint.v,
-                u: constraint.u,
-                c: constraint.c + extra_c,
-                tag: (),
-            })
+            out.push(Constraint::new(
+                constraint.v,
+                constraint.u,
+                constraint.c + extra_c,
+                (),
+            ))
         }
         // for constraint in out[..num_constraints] {}
         (out, sol)
@@ -485,24 +2287,120 @@ mod tests {
         let mut constraints: MyConstraints = x
             .iter()
             .enumerate()
-            .map(|(u, c)| Constraint {
-                v: u + 1,
-                u,
-                c: *c,
-                tag: (),
-            })
+            .map(|(u, c)| Constraint::new(u + 1, u, *c, ()))
             .collect();
         let infeasibility: i64 = rng.gen_range(1..10);
         let path_length: i64 = x.iter().sum();
-        constraints.push(Constraint {
-            v: 0,
-            u: cycle_size - 1,
-            c: -path_length - infeasibility,
-            tag: (),
-        });
+        constraints.push(Constraint::new(
+            0,
+            cycle_size - 1,
+            -path_length - infeasibility,
+            (),
+        ));
         constraints
     }
 
+    fn check_cycle<T: VarId, C: ConstraintTag>(cycle: &NegativeCycle<T, C>) {
+        let n = cycle.constraints.len();
+        for i in 0..n {
+            assert_eq!(cycle.constraints[i].v, cycle.constraints[(i + 1) % n].u);
+        }
+        assert_eq!(cycle.weight, cycle.constraints.iter().map(|c| c.c).sum());
+        assert!(cycle.weight < 0);
+    }
+
+    fn check_negative_cycle<T: VarId, C: ConstraintTag>(
+        cycle: &NegativeCycle<T, C>,
+        sys: &DCS<T, C>,
+    ) {
+        check_cycle(cycle);
+        for constraint in cycle.constraints[1..].iter() {
+            assert!(sys.all_feasible_constraints().any(|c| &c == constraint));
+        }
+    }
+
+    #[test]
+    fn test_negative_cycle_certificate() {
+        for num_vars in 2..10 {
+            for seed in 0..20 {
+                let constraints = generate_random_infeasible_cycle(num_vars, seed);
+                let mut sys = DCS::new();
+                let mut sol = Solution::new();
+                for constraint in constraints[..num_vars - 1].iter() {
+                    sol = sys.add_constraint(constraint.clone(), &sol).1.unwrap();
+                }
+                let Err(DcsError::Infeasible(cycle)) = sys
+                    .add_constraint(constraints[num_vars - 1].clone(), &sol)
+                    .1
+                else {
+                    panic!("expected a negative cycle");
+                };
+                check_negative_cycle(&cycle, &sys);
+                assert_eq!(cycle.constraints.len(), num_vars);
+                assert_eq!(cycle.constraints[0], constraints[num_vars - 1]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_minimal_conflicts() {
+        let constraints = [
+            ("b", "a", 10, "b at most 10 after a"),
+            ("c", "b", 5, "c at most 5 after b"),
+            ("d", "c", 3, "d at most 3 after c"),
+            ("a", "c", -20, "c at least 20 after a"),
+            ("a", "d", -1, "d at least 1 after a"),
+            ("d", "a", 100, "d at most 100 after a"),
+        ];
+        let (sys, sol) = DCS::from_scratch(
+            constraints
+                .into_iter()
+                .map(|(v, u, c, tag)| Constraint::new(v, u, c, tag)),
+        );
+        assert!(!sys.is_feasible());
+        let conflicts = sys.minimal_conflicts(&sol);
+        assert_eq!(conflicts.len(), 1);
+        let (rejected, mut core) = conflicts[0].clone();
+        core.sort();
+        assert_eq!(rejected, "c at least 20 after a");
+        assert_eq!(core, vec!["b at most 10 after a", "c at most 5 after b"]);
+    }
+
+    #[test]
+    fn test_deferred_add_and_solve() {
+        let (constraints, _) = generate_random_feasible_constraints(6, 20, 5, 0);
+        // shifted so that the cycle doesn't share variables with the feasible constraints.
+        let infeasible_constraints: MyConstraints = generate_random_infeasible_cycle(4, 0)
+            .into_iter()
+            .map(|constraint| Constraint {
+                v: constraint.v + 10,
+                u: constraint.u + 10,
+                ..constraint
+            })
+            .collect();
+        let mut sys = DCS::new();
+        let mut sol = Solution::new();
+        for constraint in infeasible_constraints.iter() {
+            sys.add(constraint.clone());
+        }
+        assert_eq!(sys.status(), Status::Undetermined);
+        assert_eq!(sys.solve(&mut sol), Status::Infeasible);
+        for constraint in constraints {
+            sys.add(constraint);
+        }
+        assert_eq!(sys.status(), Status::Infeasible);
+        assert_eq!(sys.solve(&mut sol), Status::Infeasible);
+        assert_eq!(sys.all_undetermined_constraints().count(), 25);
+        sol = sys
+            .remove_constraint(infeasible_constraints[0].clone(), &sol)
+            .unwrap();
+        assert_eq!(sys.status(), Status::Undetermined);
+        assert!(sys.check_solution(&sol));
+        assert_eq!(sys.solve(&mut sol), Status::Feasible);
+        assert!(sys.is_feasible());
+        assert!(sys.check_solution(&sol));
+    }
+
     #[test]
     fn test_random_infeasible_cycles() {
         for num_vars in 2..10 {
@@ -534,7 +2432,7 @@ mod tests {
         let mut constraints = shrink_constraints(
             feasible_constraints
                 .into_iter()
-                .chain(infeasible_constraints.clone().into_iter()),
+                .chain(infeasible_constraints.clone()),
         );
         // let mut constraints: MyConstraints = feasible_constraints
         //     .into_iter()
@@ -568,7 +2466,14 @@ mod tests {
                         // let vars = infeasible_constraints
                         //     .iter()
                         //     .map(|constraint| (constraint.v, constraint.u));
-                        let sol = sys.remove_constraints(infeasible_constraints.into_iter(), &sol);
+                        // some of the cycle's constraints may have been shrunk away.
+                        let to_remove: Vec<_> = infeasible_constraints
+                            .into_iter()
+                            .filter(|constraint| {
+                                sys.constraints().any(|(_, known)| known == constraint)
+                            })
+                            .collect();
+                        let sol = sys.remove_constraints(to_remove.into_iter(), &sol).unwrap();
                         assert!(sys.is_feasible());
                         assert!(sys.check_solution(&sol));
                     }
@@ -595,33 +2500,694 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_from_scratch_batch() {
+        for num_vars in 2..10 {
+            for seed in 0..10 {
+                let (constraints, _) = generate_random_feasible_constraints(
+                    num_vars,
+                    num_vars * (num_vars - 1) / 2,
+                    num_vars,
+                    seed,
+                );
+                let (sys, sol) = DCS::from_scratch_batch(constraints.into_iter());
+                assert!(sys.is_feasible());
+                assert!(sys.check_solution(&sol));
+                // like from_scratch, the last constraint of the cycle is the one left out.
+                let cycle = generate_random_infeasible_cycle(num_vars, seed);
+                let (sys, sol) = DCS::from_scratch_batch(cycle.clone().into_iter());
+                let (expected_sys, _) = DCS::from_scratch(cycle.clone().into_iter());
+                assert!(sys.check_solution(&sol));
+                assert_eq!(
+                    sys.all_infeasible_constraints().collect::<Vec<_>>(),
+                    vec![cycle.last().unwrap().clone()]
+                );
+                assert_eq!(
+                    sys.all_infeasible_constraints().collect::<Vec<_>>(),
+                    expected_sys
+                        .all_infeasible_constraints()
+                        .collect::<Vec<_>>()
+                );
+            }
+        }
+        for num_vars in 3usize..8 {
+            for seed in 0..3 {
+                let (constraints, _) = generate_random_infeasible_system(
+                    num_vars,
+                    num_vars * (num_vars - 1),
+                    num_vars,
+                    num_vars,
+                    seed,
+                );
+                let (sys, sol) = DCS::from_scratch_batch(constraints.clone().into_iter());
+                let (expected_sys, _) = DCS::from_scratch(constraints.clone().into_iter());
+                assert!(!sys.is_feasible());
+                assert!(!expected_sys.is_feasible());
+                assert!(sys.check_solution(&sol));
+                assert_eq!(sys.constraints().count(), constraints.len());
+                // every constraint that was left out conflicts with the ones that were kept.
+                for constraint in sys.all_infeasible_constraints() {
+                    let Err(DcsError::Infeasible(cycle)) =
+                        sys.check_and_solve_new_constraint(&constraint, &sol)
+                    else {
+                        panic!("expected a negative cycle");
+                    };
+                    check_cycle(&cycle);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_overflow() {
+        let mut sys = DCS::new();
+        let (_, sol) = sys.add_constraint(Constraint::new(1, 0, i64::MIN, ()), &Solution::new());
+        let sol = sol.unwrap();
+        assert_eq!(sys.get_implied_ub(&1, &0, &sol), Ok(Some(i64::MIN)));
+        assert_eq!(sys.get_implied_lb(&1, &0, &sol), Err(DcsError::Overflow));
+
+        // the value of 2 can't be represented, so the constraint is left undetermined.
+        let (id, result) = sys.add_constraint(Constraint::new(2, 1, -10, ()), &sol);
+        assert_eq!(result.unwrap_err(), DcsError::Overflow);
+        assert_eq!(sys.status(), Status::Undetermined);
+        assert!(sys.check_solution(&sol));
+        let mut sol = sys.remove_by_id(id, &sol).unwrap();
+        assert!(sys.is_feasible());
+
+        sys.add_constraint(Constraint::new(3, 2, i64::MAX, ()), &sol)
+            .1
+            .unwrap();
+        let (_, result) = sys.add_constraint(Constraint::new(2, 1, i64::MAX, ()), &sol);
+        sol = result.unwrap();
+        assert_eq!(sys.get_implied_ub(&3, &1, &sol), Err(DcsError::Overflow));
+        // the batch falls back to adding the constraints one by one.
+        let (sys, sol) = DCS::from_scratch_batch(
+            [
+                Constraint::new(1, 0, i64::MIN, ()),
+                Constraint::new(2, 1, -1, ()),
+            ]
+            .into_iter(),
+        );
+        assert_eq!(sys.status(), Status::Undetermined);
+        assert!(sys.check_solution(&sol));
+
+        // a bound so loose that the value it allows overflows doesn't change anything.
+        let mut sys = DCS::new();
+        let (_, result) = sys.add_constraint(Constraint::new(1, 0, -5, ()), &Solution::new());
+        let mut sol = result.unwrap();
+        sol.normalize(None).unwrap();
+        let (_, result) = sys.add_constraint(Constraint::new(2, 0, i64::MAX, ()), &sol);
+        let sol = result.unwrap();
+        assert!(sys.is_feasible());
+        assert!(sys.check_solution(&sol));
+        assert_eq!(sol.get(&2), Some(&0));
+
+        // a difference that overflows is decided by its sign.
+        let sol: Solution<i32> = [(0, i64::MAX), (1, i64::MIN)].into_iter().collect();
+        assert!(sol.check_constraint(&Constraint::new(1, 0, 0, ())));
+        assert!(!sol.check_constraint(&Constraint::new(0, 1, 0, ())));
+    }
+
+    #[test]
+    fn test_weight_types() {
+        for seed in 0..10 {
+            let (constraints, _) = generate_random_feasible_constraints(6, 15, 3, seed);
+            let (narrow, narrow_sol) = DCS::from_scratch(constraints.iter().map(|constraint| {
+                Constraint::new(constraint.v, constraint.u, constraint.c as i32, ())
+            }));
+            let (wide, wide_sol) = DCS::from_scratch(constraints.iter().map(|constraint| {
+                Constraint::new(constraint.v, constraint.u, constraint.c as i128, ())
+            }));
+            assert!(narrow.is_feasible() && wide.is_feasible());
+            assert!(narrow.check_solution(&narrow_sol));
+            assert!(wide.check_solution(&wide_sol));
+            for x in 0..6 {
+                for y in 0..6 {
+                    let narrow_ub = narrow.get_implied_ub(&x, &y, &narrow_sol).ok().flatten();
+                    let wide_ub = wide.get_implied_ub(&x, &y, &wide_sol).ok().flatten();
+                    assert_eq!(narrow_ub.map(i128::from), wide_ub);
+                }
+            }
+        }
+
+        let mut sys = DCS::new();
+        let mut sol = Solution::new();
+        for (v, u) in [(1, 0), (2, 1), (3, 2)] {
+            sol = sys
+                .add_constraint(Constraint::new(v, u, i64::MIN as i128, ()), &sol)
+                .1
+                .unwrap();
+        }
+        assert_eq!(
+            sys.get_implied_ub(&3, &0, &sol),
+            Ok(Some(3 * i64::MIN as i128))
+        );
+        let mut sys = DCS::new();
+        let (_, sol) = sys.add_constraint(Constraint::new(1, 0, i32::MIN, ()), &Solution::new());
+        let (_, result) = sys.add_constraint(Constraint::new(2, 1, -1, ()), &sol.unwrap());
+        assert_eq!(result.unwrap_err(), DcsError::Overflow);
+    }
+
+    #[test]
+    fn test_strict_constraints() {
+        assert_eq!(Constraint::strict(1, 0, 3, ()).to_string(), "1 - 0 < 3");
+
+        // over the integers, 1 - 0 < 1 is 1 - 0 <= 0.
+        let (sys, sol) = DCS::from_scratch(
+            [
+                Constraint::strict(1, 0, 1, ()),
+                Constraint::new(0, 1, 0, ()),
+            ]
+            .into_iter(),
+        );
+        assert!(sys.is_feasible());
+        assert!(sys.check_solution(&sol));
+        assert_eq!(sys.get_implied_ub(&1, &0, &sol), Ok(Some(0)));
+
+        // a zero weight cycle is infeasible once one of its edges is strict.
+        let mut sys = DCS::new();
+        let mut sol = Solution::new();
+        for (v, u, c) in [(1, 0, 2), (2, 1, -1)] {
+            sol = sys
+                .add_constraint(Constraint::new(v, u, c, ()), &sol)
+                .1
+                .unwrap();
+        }
+        let (_, result) = sys.add_constraint(Constraint::strict(0, 2, -1, ()), &sol);
+        let Err(DcsError::Infeasible(cycle)) = result else {
+            panic!("expected a negative cycle");
+        };
+        assert_eq!(cycle.weight, -1);
+        assert_eq!(cycle.constraints.len(), 3);
+        assert!(!DCS::from_scratch_batch(
+            [
+                Constraint::new(1, 0, 2, ()),
+                Constraint::new(2, 1, -1, ()),
+                Constraint::strict(0, 2, -1, ()),
+            ]
+            .into_iter()
+        )
+        .0
+        .is_feasible());
+        sol = sys
+            .add_constraint(Constraint::new(0, 2, -1, ()), &sol)
+            .1
+            .unwrap();
+        assert!(!sol.check_constraint(&Constraint::strict(0, 2, -1, ())));
+
+        // there is nothing strictly below i64::MIN.
+        let (_, result) = sys.add_constraint(Constraint::strict(3, 0, i64::MIN, ()), &sol);
+        assert_eq!(result.unwrap_err(), DcsError::Overflow);
+    }
+
+    type Snapshot<T, C> = (
+        Vec<Vec<Constraint<T, C>>>,
+        Vec<(T, i64)>,
+        Vec<(ConstraintId, Constraint<T, C>)>,
+    );
+
+    fn snapshot<T: VarId + Ord, C: ConstraintTag + Ord>(
+        sys: &DCS<T, C>,
+        sol: &Solution<T>,
+    ) -> Snapshot<T, C> {
+        let stores = [
+            &sys.feasible_constraints,
+            &sys.infeasible_constraints,
+            &sys.undetermined_constraints,
+        ];
+        let constraints = stores
+            .into_iter()
+            .map(|store| {
+                let mut constraints: Vec<Constraint<T, C>> = store.to_all_constraints().collect();
+                constraints.sort();
+                constraints
+            })
+            .collect();
+        let mut values: Vec<(T, i64)> = sol.0.iter().map(|(x, v)| (x.clone(), *v)).collect();
+        values.sort();
+        let mut handles: Vec<(ConstraintId, Constraint<T, C>)> = sys
+            .constraints()
+            .map(|(id, constraint)| (id, constraint.clone()))
+            .collect();
+        handles.sort();
+        (constraints, values, handles)
+    }
+
+    #[test]
+    fn test_push_pop() {
+        use rand::prelude::*;
+        for seed in 0..20 {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            let (constraints, _) = generate_random_infeasible_system(6, 20, 4, 5, seed);
+            let (mut sys, mut sol) = DCS::from_scratch(constraints[..10].iter().cloned());
+            let mut snapshots = vec![];
+            for constraint in constraints[10..].iter() {
+                if rng.gen_bool(0.3) {
+                    snapshots.push(snapshot(&sys, &sol));
+                    sys.push();
+                }
+                if let (_, Ok(new_sol)) = sys.add_constraint(constraint.clone(), &sol) {
+                    sol = new_sol;
+                }
+                if rng.gen_bool(0.2) {
+                    sys.add(constraint.clone());
+                    sys.solve(&mut sol);
+                }
+                if rng.gen_bool(0.3) {
+                    let to_remove = constraints.choose(&mut rng).unwrap().clone();
+                    match sys.remove_constraint(to_remove, &sol) {
+                        Ok(new_sol) => sol = new_sol,
+                        Err(DcsError::UnknownConstraint(_)) => {}
+                        Err(err) => panic!("{}", err),
+                    }
+                }
+                assert!(sys.check_solution(&sol));
+            }
+            while let Some(expected) = snapshots.pop() {
+                assert!(sys.pop(&mut sol));
+                assert_eq!(snapshot(&sys, &sol), expected);
+                assert!(sys.check_solution(&sol));
+            }
+            assert_eq!(sys.num_scopes(), 0);
+            assert!(!sys.pop(&mut sol));
+        }
+    }
+
+    #[test]
+    fn test_watch_events() {
+        use rand::prelude::*;
+        for seed in 0..20 {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            let num_vars = 7;
+            let (constraints, _) = generate_random_feasible_constraints(num_vars, 25, 5, seed);
+            let mut sys = DCS::new();
+            let mut sol = Solution::new();
+            let watched: Vec<Constraint<usize, usize>> = (0..30)
+                .map(|tag| {
+                    Constraint::new(
+                        rng.gen_range(0..num_vars),
+                        rng.gen_range(0..num_vars),
+                        rng.gen_range(-100..100),
+                        tag,
+                    )
+                })
+                .collect();
+            let mut fired = HashSet::new();
+            for (i, constraint) in constraints.into_iter().enumerate() {
+                if i == 10 {
+                    for constraint in watched.iter() {
+                        sys.watch(constraint.clone(), &sol).unwrap();
+                    }
+                }
+                let constraint =
+                    Constraint::new(constraint.v, constraint.u, constraint.c, usize::MAX);
+                sol = sys.add_constraint(constraint, &sol).1.unwrap();
+                for event in sys.take_watch_events() {
+                    let (WatchEvent::Implied(constraint) | WatchEvent::Impossible(constraint)) =
+                        &event;
+                    assert!(fired.insert(constraint.tag));
+                    // a watched x - x <= c may fire before x is known to the system.
+                    let implied_ub = |x: &usize, y: &usize| match x == y {
+                        true => 0,
+                        false => sys.get_implied_ub(x, y, &sol).unwrap().unwrap(),
+                    };
+                    match event {
+                        WatchEvent::Implied(c) => assert!(implied_ub(&c.v, &c.u) <= c.c),
+                        WatchEvent::Impossible(c) => assert!(implied_ub(&c.u, &c.v) + c.c < 0),
+                    }
+                }
+                for c in sys.all_watched_constraints() {
+                    // watched constraints may mention variables the system doesn't know yet.
+                    assert!(sys
+                        .get_implied_ub(&c.v, &c.u, &sol)
+                        .unwrap_or_default()
+                        .is_none_or(|ub| ub > c.c));
+                    assert!(sys
+                        .get_implied_ub(&c.u, &c.v, &sol)
+                        .unwrap_or_default()
+                        .is_none_or(|ub| ub + c.c >= 0));
+                }
+            }
+            assert_eq!(fired.len() + sys.all_watched_constraints().count(), 30);
+        }
+
+        // a path through the new constraint that underflows still decides the watched ones,
+        // and doesn't keep the constraint from being added.
+        let mut sys = DCS::new();
+        let sol: Solution<i32> = [(0, i64::MAX), (1, -1)].into_iter().collect();
+        let (_, result) = sys.add_constraint(Constraint::new(1, 0, i64::MIN, ()), &sol);
+        let sol = result.unwrap();
+        sys.watch(Constraint::new(2, 0, 0, ()), &sol).unwrap();
+        sys.watch(Constraint::new(0, 2, 0, ()), &sol).unwrap();
+        let (_, result) = sys.add_constraint(Constraint::new(2, 1, -5, ()), &sol);
+        assert!(sys.check_solution(&result.unwrap()));
+        assert!(sys.is_feasible());
+        assert_eq!(
+            sys.take_watch_events(),
+            vec![
+                WatchEvent::Implied(Constraint::new(2, 0, 0, ())),
+                WatchEvent::Impossible(Constraint::new(0, 2, 0, ())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_constraint_ids() {
+        let mut sys = DCS::new();
+        let (id1, sol) = sys.add_constraint(Constraint::new(1, 0, 5, ()), &Solution::new());
+        let (id2, sol) = sys.add_constraint(Constraint::new(1, 0, 5, ()), &sol.unwrap());
+        let (id3, sol) = sys.add_constraint(Constraint::new(2, 1, 5, ()), &sol.unwrap());
+        let sol = sol.unwrap();
+        assert_ne!(id1, id2);
+        assert_eq!(sys.get(id2), Some(&Constraint::new(1, 0, 5, ())));
+
+        // identical constraints share an edge until the last handle is removed.
+        let sol = sys.remove_by_id(id1, &sol).unwrap();
+        assert_eq!(sys.get(id1), None);
+        assert_eq!(sys.get_implied_ub(&2, &0, &sol), Ok(Some(10)));
+        let sol = sys.remove_by_id(id2, &sol).unwrap();
+        assert_eq!(
+            sys.get_implied_ub(&2, &0, &sol),
+            Err(DcsError::UnknownVariable(0))
+        );
+        assert_eq!(sys.get_implied_ub(&1, &2, &sol), Ok(None));
+        assert_eq!(
+            sys.remove_by_id(id2, &sol).unwrap_err(),
+            DcsError::UnknownConstraintId(id2)
+        );
+
+        let (_, sol) = sys.add_constraint(Constraint::new(1, 0, 0, ()), &sol);
+        let (id4, sol) = sys.add_constraint(Constraint::new(0, 2, -3, ()), &sol.unwrap());
+        let mut sol = sol.unwrap();
+        let before = sol.clone();
+        let Err(DcsError::Infeasible(cycle)) = sys.update_bound(id4, -6, &mut sol) else {
+            panic!("expected a negative cycle");
+        };
+        assert_eq!(cycle.weight, -1);
+        // an infeasible bound is kept, and sol is left as it was.
+        assert_eq!(sys.get(id4), Some(&Constraint::new(0, 2, -6, ())));
+        assert!(!sys.is_feasible());
+        assert_eq!(sol.iter().count(), before.iter().count());
+        assert!(sol.iter().all(|(var, val)| before.get(var) == Some(val)));
+        // relaxing it to a bound that is still infeasible keeps the new bound as well.
+        assert!(sys.update_bound(id4, -100, &mut sol).is_err());
+        let Err(DcsError::Infeasible(cycle)) = sys.update_bound(id4, -50, &mut sol) else {
+            panic!("expected a negative cycle");
+        };
+        assert_eq!(cycle.weight, -45);
+        assert_eq!(sys.get(id4), Some(&Constraint::new(0, 2, -50, ())));
+        assert_eq!(sys.all_infeasible_constraints().count(), 1);
+        assert!(sol.iter().all(|(var, val)| before.get(var) == Some(val)));
+        let delta = sys.update_bound(id4, -5, &mut sol).unwrap();
+        assert!(sys.is_feasible());
+        assert!(!delta.is_empty());
+        delta.revert(&mut sol);
+        assert!(sol.iter().all(|(var, val)| before.get(var) == Some(val)));
+        delta.apply(&mut sol);
+        assert!(sys.check_solution(&sol));
+        sys.update_bound(id3, 6, &mut sol).unwrap();
+        sys.update_bound(id4, -6, &mut sol).unwrap();
+        assert!(sys.is_feasible());
+        assert!(sys.check_solution(&sol));
+        assert_eq!(sys.get(id3), Some(&Constraint::new(2, 1, 6, ())));
+        assert_eq!(sys.constraints().count(), 3);
+    }
+
+    #[test]
+    fn test_update_bound_onto_identical_constraint() {
+        let is_empty = |sys: &DCS<i32, ()>| {
+            sys.constraints().count() == 0
+                && sys.all_feasible_constraints().count() == 0
+                && sys.all_infeasible_constraints().count() == 0
+                && sys.all_undetermined_constraints().count() == 0
+        };
+        // a feasible handle relaxed onto a constraint that is waiting for `solve`.
+        let mut sys = DCS::new();
+        let waiting = sys.add(Constraint::new(1, 0, 5, ()));
+        let (id, sol) = sys.add_constraint(Constraint::new(1, 0, 3, ()), &Solution::new());
+        let mut sol = sol.unwrap();
+        sys.relax(id, 5, &mut sol).unwrap();
+        assert_eq!(sys.all_undetermined_constraints().count(), 0);
+        sol = sys.remove_by_id(waiting, &sol).unwrap();
+        sys.remove_by_id(id, &sol).unwrap();
+        assert!(is_empty(&sys));
+
+        // a waiting handle relaxed onto a feasible constraint.
+        let mut sys = DCS::new();
+        let waiting = sys.add(Constraint::new(1, 0, 3, ()));
+        let (id, sol) = sys.add_constraint(Constraint::new(1, 0, 5, ()), &Solution::new());
+        let mut sol = sol.unwrap();
+        sys.update_bound(waiting, 5, &mut sol).unwrap();
+        assert_eq!(sys.all_feasible_constraints().count(), 1);
+        assert_eq!(sys.all_undetermined_constraints().count(), 0);
+        sol = sys.remove_by_id(id, &sol).unwrap();
+        sys.remove_by_id(waiting, &sol).unwrap();
+        assert!(is_empty(&sys));
+    }
+
+    #[test]
+    fn test_solution_accessors() {
+        let (constraints, _) = generate_random_feasible_constraints(6, 15, 0, 0);
+        let mut sys = DCS::new();
+        let mut sol = Solution::new();
+        for constraint in constraints.iter() {
+            sol = sys.add_constraint(constraint.clone(), &sol).1.unwrap();
+        }
+        // every variable that appears in a constraint has a value.
+        let mut vars: Vec<usize> = sol.variables().cloned().collect();
+        vars.sort();
+        assert_eq!(vars, (0..6).collect::<Vec<_>>());
+        assert_eq!(sol.len(), 6);
+        assert!(!sol.is_empty());
+        for (var, val) in sol.iter() {
+            assert_eq!(sol[var], *val);
+            assert_eq!(sol.get(var), Some(val));
+        }
+        assert_eq!(sol.get(&6), None);
+
+        assert_eq!(sol.normalize(Some(&3)), Ok(()));
+        assert_eq!(sol[&3], 0);
+        assert!(sys.check_solution(&sol));
+        assert_eq!(sol.normalize(None), Ok(()));
+        assert_eq!(sol.iter().map(|(_, val)| *val).min(), Some(0));
+        assert!(sys.check_solution(&sol));
+        assert_eq!(
+            sol.normalize(Some(&6)),
+            Err(SolutionError::UnknownVariable(6))
+        );
+        let total: i64 = (&sol).into_iter().map(|(_, val)| val).sum();
+        assert_eq!(total, sol.into_iter().map(|(_, val)| val).sum());
+
+        let mut sol: Solution<i32> = [(0, i64::MIN), (1, i64::MAX)].into_iter().collect();
+        assert_eq!(sol.normalize(None), Err(SolutionError::Overflow));
+        assert_eq!(sol[&1], i64::MAX);
+        assert_eq!(Solution::<i32>::new().normalize(None), Ok(()));
+        let err: DcsError<i32, ()> = SolutionError::UnknownVariable(6).into();
+        assert_eq!(err, DcsError::UnknownVariable(6));
+    }
+
+    #[test]
+    fn test_solution_delta() {
+        let same = |a: &Solution<usize>, b: &Solution<usize>| {
+            (0..8).all(|var| a.get_or(&var, 0) == b.get_or(&var, 0))
+        };
+        for seed in 0..10 {
+            let (constraints, _) = generate_random_feasible_constraints(8, 20, 3, seed);
+            let mut sys = DCS::new();
+            let mut sol = Solution::new();
+            let mut ids = vec![];
+            for constraint in constraints {
+                let before = sol.clone();
+                let expected = sys
+                    .check_and_solve_new_constraint(&constraint, &sol)
+                    .unwrap();
+                let (id, delta) = sys.add_constraint_in_place(constraint, &mut sol);
+                let delta = delta.unwrap();
+                ids.push(id);
+                assert_eq!(delta.len(), expected.len());
+                let mut applied = before.clone();
+                delta.apply(&mut applied);
+                assert!(same(&applied, &sol));
+                delta.revert(&mut applied);
+                assert!(same(&applied, &before));
+                assert!(delta.iter().all(|(var, old, new)| {
+                    before.get(var).cloned() == old && sol.get(var) == Some(&new)
+                }));
+            }
+            assert!(sys.check_solution(&sol));
+            for id in ids.into_iter().step_by(3) {
+                let before = sol.clone();
+                let delta = sys.remove_by_id_in_place(id, &mut sol).unwrap();
+                let mut reverted = sol.clone();
+                delta.revert(&mut reverted);
+                assert!(same(&reverted, &before));
+                assert!(sys.check_solution(&sol));
+            }
+        }
+
+        // a failed operation leaves sol alone.
+        let mut sys = DCS::new();
+        let mut sol = Solution::new();
+        let (interval, delta) = sys.add_interval_in_place(1, 0, 2, 4, (), &mut sol).unwrap();
+        assert_eq!(delta.len(), 2);
+        let before = sol.clone();
+        assert!(sys.add_interval_in_place(0, 1, 1, 3, (), &mut sol).is_err());
+        assert!(same(&sol, &before));
+        let (_, result) = sys.add_constraint_in_place(Constraint::new(0, 1, -5, ()), &mut sol);
+        assert!(result.is_err());
+        assert!(same(&sol, &before));
+        sys.remove_interval_in_place(interval, &mut sol).unwrap();
+        assert!(sys.remove_interval_in_place(interval, &mut sol).is_err());
+    }
+
+    #[test]
+    fn test_intervals() {
+        let mut sys = DCS::new();
+        let (_, sol) = sys.add_constraint(Constraint::new("x", "y", 2, 1), &Solution::new());
+        let (interval, sol) = sys.add_interval("x", "y", -3, 5, 2, &sol.unwrap()).unwrap();
+        let (_, sol) = sys.add_equality("z", "x", 4, 3, &sol).unwrap();
+        assert!(sys.check_solution(&sol));
+        assert_eq!(sys.get_interval(interval), Some((-3, 5)));
+        assert_eq!(sys.get_implied_ub(&"z", &"y", &sol), Ok(Some(6)));
+        assert_eq!(sys.get_implied_ub(&"y", &"z", &sol), Ok(Some(-1)));
+
+        // the upper half fits, the lower one doesn't, so neither is kept.
+        sys.push();
+        let Err(DcsError::Infeasible(cycle)) = sys.add_interval("x", "y", 3, 4, 4, &sol) else {
+            panic!("expected a negative cycle");
+        };
+        let mut tags: Vec<i32> = cycle.tags().cloned().collect();
+        tags.sort();
+        assert_eq!(tags, vec![1, 4]);
+        // the rejected half is y - x <= -3, i.e. the lower bound of the interval, which is reported as a whole.
+        assert_eq!(cycle.constraints[0], Constraint::new("y", "x", -3, 4));
+        let [CycleElement::Interval {
+            v: "x",
+            u: "y",
+            lo: 3,
+            hi: 4,
+            tag: 4,
+            ..
+        }, CycleElement::Constraint(constraint)] = &cycle.elements[..]
+        else {
+            panic!("expected the interval and a constraint");
+        };
+        assert_eq!(constraint.tag, 1);
+        assert_eq!(sys.constraints().count(), 1);
+        assert_eq!(sys.intervals().count(), 2);
+        assert!(sys.is_feasible());
+        let Err(DcsError::Infeasible(cycle)) = sys.add_equality("x", "z", 0, 5, &sol) else {
+            panic!("expected a negative cycle");
+        };
+        assert!(cycle.tags().all(|tag| [3, 5].contains(tag)));
+        let mut intervals: Vec<(i64, i64, i32)> = cycle
+            .elements
+            .iter()
+            .map(|element| match element {
+                CycleElement::Interval { lo, hi, tag, .. } => (*lo, *hi, *tag),
+                CycleElement::Constraint(_) => panic!("expected intervals only"),
+            })
+            .collect();
+        intervals.sort();
+        assert_eq!(intervals, vec![(0, 0, 5), (4, 4, 3)]);
+        assert_eq!(sys.num_scopes(), 1);
+
+        // a constraint that conflicts with an interval that is already in the system.
+        let (_, result) = sys.add_constraint(Constraint::new("x", "y", -4, 7), &sol);
+        let Err(DcsError::Infeasible(cycle)) = result else {
+            panic!("expected a negative cycle");
+        };
+        assert_eq!(
+            cycle.elements,
+            vec![
+                CycleElement::Constraint(Constraint::new("x", "y", -4, 7)),
+                CycleElement::Interval {
+                    id: interval,
+                    v: "x",
+                    u: "y",
+                    lo: -3,
+                    hi: 5,
+                    tag: 2
+                }
+            ]
+        );
+
+        let (_, mut sol) = sys.add_interval("w", "x", 0, 1, 6, &sol).unwrap();
+        sys.pop(&mut sol);
+        assert_eq!(sys.constraints().count(), 1);
+        assert_eq!(sys.intervals().count(), 2);
+
+        // the halves can only be removed together.
+        assert_eq!(
+            sys.remove_by_id(interval.0, &sol).unwrap_err(),
+            DcsError::UnknownConstraintId(interval.0)
+        );
+        assert!(sys
+            .remove_constraint(Constraint::new("y", "x", 3, 2), &sol)
+            .is_err());
+        assert_eq!(sys.get_interval(interval), Some((-3, 5)));
+
+        let sol = sys.remove_interval(interval, &sol).unwrap();
+        assert_eq!(sys.constraints().count(), 1);
+        assert_eq!(sys.intervals().count(), 1);
+        assert!(sys.get_interval(interval).is_none());
+        assert!(sys.remove_interval(interval, &sol).is_err());
+        assert_eq!(sys.get_implied_ub(&"y", &"x", &sol), Ok(None));
+    }
+
+    #[test]
+    fn test_tighten_relax() {
+        use rand::prelude::*;
+        for seed in 0..30 {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            let (constraints, _) = generate_random_infeasible_system(6, 15, 4, 3, seed);
+            let mut sys = DCS::new();
+            let mut sol = Solution::new();
+            let mut ids = vec![];
+            for constraint in constraints {
+                let (id, result) = sys.add_constraint(constraint, &sol);
+                if let Ok(new_sol) = result {
+                    sol = new_sol;
+                }
+                ids.push(id);
+            }
+            for _ in 0..30 {
+                let id = *ids.choose(&mut rng).unwrap();
+                let c = sys.get(id).unwrap().c;
+                let (new_c, result) = if rng.gen_bool(0.5) {
+                    let new_c = c - rng.gen_range(0..30);
+                    (new_c, sys.tighten(id, new_c, &mut sol))
+                } else {
+                    let new_c = c + rng.gen_range(0..30);
+                    (new_c, sys.relax(id, new_c, &mut sol))
+                };
+                match result {
+                    Ok(_) => {}
+                    Err(DcsError::Infeasible(cycle)) => check_negative_cycle(&cycle, &sys),
+                    Err(err) => panic!("{}", err),
+                }
+                // an infeasible bound is kept, like an infeasible constraint is.
+                assert_eq!(sys.get(id).unwrap().c, new_c);
+                assert!(sys.check_solution(&sol));
+                for constraint in sys.all_infeasible_constraints() {
+                    assert!(sys
+                        .check_and_solve_new_constraint(&constraint, &sol)
+                        .is_err());
+                }
+                let (rebuilt, _) =
+                    DCS::from_scratch(sys.constraints().map(|(_, constraint)| constraint.clone()));
+                assert_eq!(sys.is_feasible(), rebuilt.is_feasible());
+            }
+        }
+    }
+
     #[test]
     fn test_infeasible_system() {
         let constraints = [
-            Constraint {
-                v: 0,
-                u: 1,
-                c: 40,
-                tag: (),
-            },
-            Constraint {
-                v: 2,
-                u: 1,
-                c: 6,
-                tag: (),
-            },
-            Constraint {
-                v: 0,
-                u: 2,
-                c: -60,
-                tag: (),
-            },
-            Constraint {
-                v: 1,
-                u: 0,
-                c: -40,
-                tag: (),
-            },
+            Constraint::new(0, 1, 40, ()),
+            Constraint::new(2, 1, 6, ()),
+            Constraint::new(0, 2, -60, ()),
+            Constraint::new(1, 0, -40, ()),
         ];
         expect_infeasible(constraints.into_iter());
     }
@@ -629,48 +3195,37 @@ mod tests {
     #[test]
     fn test_remove_constraint() {
         let constraints = [
-            Constraint {
-                v: 0,
-                u: 1,
-                c: 40,
-                tag: (),
-            },
-            Constraint {
-                v: 2,
-                u: 1,
-                c: 6,
-                tag: (),
-            },
-            Constraint {
-                v: 0,
-                u: 2,
-                c: -60,
-                tag: (),
-            },
-            Constraint {
-                v: 1,
-                u: 0,
-                c: -40,
-                tag: (),
-            },
+            Constraint::new(0, 1, 40, ()),
+            Constraint::new(2, 1, 6, ()),
+            Constraint::new(0, 2, -60, ()),
+            Constraint::new(1, 0, -40, ()),
         ];
         let (mut sys, sol) = DCS::from_scratch(constraints.clone().into_iter());
-        sys.remove_constraint(constraints[3].clone(), &sol);
+        let sol = sys.remove_constraint(constraints[3].clone(), &sol).unwrap();
         assert!(sys.is_feasible());
         assert!(sys.check_solution(&sol));
-        sys.add_constraint(
-            Constraint {
-                v: 1,
-                u: 0,
-                c: -30,
-                tag: (),
-            },
-            &sol,
-        );
+        sys.add_constraint(Constraint::new(1, 0, -30, ()), &sol)
+            .1
+            .unwrap_err();
         assert!(!sys.is_feasible());
         assert!(sys.check_solution(&sol));
-        sys.remove_constraint(constraints[2].clone(), &sol);
+        let sol = sys.remove_constraint(constraints[2].clone(), &sol).unwrap();
         assert!(sys.is_feasible());
         assert!(sys.check_solution(&sol));
+        assert_eq!(
+            sys.remove_constraint(constraints[2].clone(), &sol)
+                .unwrap_err(),
+            DcsError::UnknownConstraint(constraints[2].clone())
+        );
+        // nothing is removed if one of the constraints is unknown.
+        assert_eq!(
+            sys.remove_constraints(
+                [constraints[0].clone(), constraints[3].clone()].into_iter(),
+                &sol
+            )
+            .unwrap_err(),
+            DcsError::UnknownConstraint(constraints[3].clone())
+        );
+        assert_eq!(sys.all_feasible_constraints().count(), 3);
     }
 }
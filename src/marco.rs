@@ -0,0 +1,247 @@
+use crate::{Constraint, ConstraintTag, DcsError, DcsResult, Solution, VarId, Weight, DCS};
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum MinimalSubset<C: ConstraintTag> {
+    // a minimal unsatisfiable subset: infeasible, but feasible once any of its constraints is dropped.
+    Unsatisfiable(Vec<C>),
+    // a minimal correction set: dropping it makes the collection feasible, dropping any proper subset doesn't.
+    Correction(Vec<C>),
+}
+
+pub struct Marco<T: VarId, C: ConstraintTag, W: Weight = i64> {
+    constraints: Vec<Constraint<T, C, W>>,
+    // each clause is (polarity, indices): a seed must contain at least one of the indices if polarity is true,
+    // and must miss at least one of them otherwise.
+    clauses: Vec<(bool, Vec<usize>)>,
+    // set once a check overflows, which ends the enumeration.
+    failed: bool,
+}
+
+impl<T: VarId, C: ConstraintTag, W: Weight> Marco<T, C, W> {
+    pub fn new<It>(constraints: It) -> Self
+    where
+        It: IntoIterator<Item = Constraint<T, C, W>>,
+    {
+        Marco {
+            constraints: constraints.into_iter().collect(),
+            clauses: vec![],
+            failed: false,
+        }
+    }
+    fn tags(&self, indices: &[usize]) -> Vec<C> {
+        indices
+            .iter()
+            .map(|i| self.constraints[*i].tag.clone())
+            .collect()
+    }
+    fn indexed(&self, i: usize) -> Constraint<T, usize, W> {
+        let constraint = &self.constraints[i];
+        Constraint {
+            v: constraint.v.clone(),
+            u: constraint.u.clone(),
+            c: constraint.c,
+            strict: constraint.strict,
+            tag: i,
+        }
+    }
+    fn fail(&mut self, err: DcsError<T, usize, W>) -> DcsError<T, C, W> {
+        // the seed can't be classified, which ends the enumeration.
+        self.failed = true;
+        match err {
+            DcsError::StrictUnsupported => DcsError::StrictUnsupported,
+            _ => DcsError::Overflow,
+        }
+    }
+    fn find_seed(&self) -> Option<Vec<bool>> {
+        let mut assignment = vec![None; self.constraints.len()];
+        if self.dpll(&mut assignment) {
+            Some(assignment.into_iter().map(|a| a.unwrap()).collect())
+        } else {
+            None
+        }
+    }
+    fn dpll(&self, assignment: &mut Vec<Option<bool>>) -> bool {
+        // unit propagation, then branch on the first unassigned index, preferring to include it
+        // so that seeds tend to be large and growing them is cheap.
+        let mut propagated = vec![];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (polarity, indices) in self.clauses.iter() {
+                let mut unassigned = None;
+                let mut num_unassigned = 0;
+                let mut satisfied = false;
+                for i in indices.iter() {
+                    match assignment[*i] {
+                        Some(value) if value == *polarity => {
+                            satisfied = true;
+                            break;
+                        }
+                        Some(_) => {}
+                        None => {
+                            unassigned = Some(*i);
+                            num_unassigned += 1;
+                        }
+                    }
+                }
+                if satisfied || num_unassigned > 1 {
+                    continue;
+                }
+                let Some(i) = unassigned else {
+                    for i in propagated {
+                        assignment[i] = None;
+                    }
+                    return false;
+                };
+                assignment[i] = Some(*polarity);
+                propagated.push(i);
+                changed = true;
+            }
+        }
+        let Some(i) = assignment.iter().position(|a| a.is_none()) else {
+            return true;
+        };
+        for value in [true, false] {
+            assignment[i] = Some(value);
+            if self.dpll(assignment) {
+                return true;
+            }
+        }
+        assignment[i] = None;
+        for i in propagated {
+            assignment[i] = None;
+        }
+        false
+    }
+}
+
+impl<T: VarId, C: ConstraintTag, W: Weight> Iterator for Marco<T, C, W> {
+    // an overflow while checking a seed leaves it unclassified, so it is yielded as an error and ends the iteration.
+    type Item = DcsResult<MinimalSubset<C>, T, C, W>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let seed = self.find_seed()?;
+        let mut sys = DCS::new();
+        let mut sol = Solution::new();
+        for i in (0..seed.len()).filter(|i| seed[*i]) {
+            match sys.add_constraint(self.indexed(i), &sol).1 {
+                Ok(new_sol) => sol = new_sol,
+                Err(DcsError::Infeasible(cycle)) => {
+                    // the seed is infeasible, and the cycle that closed it is already minimal.
+                    let mus: Vec<usize> = cycle.tags().cloned().collect();
+                    let out = self.tags(&mus);
+                    self.clauses.push((false, mus));
+                    return Some(Ok(MinimalSubset::Unsatisfiable(out)));
+                }
+                Err(err) => return Some(Err(self.fail(err))),
+            }
+        }
+        // the seed is feasible, grow it to a maximal feasible subset. its complement is a correction set.
+        let mut mcs = vec![];
+        for i in (0..seed.len()).filter(|i| !seed[*i]) {
+            match sys.add_constraint(self.indexed(i), &sol).1 {
+                Ok(new_sol) => sol = new_sol,
+                Err(DcsError::Infeasible(_)) => mcs.push(i),
+                Err(err) => return Some(Err(self.fail(err))),
+            }
+        }
+        let out = self.tags(&mcs);
+        self.clauses.push((true, mcs));
+        Some(Ok(MinimalSubset::Correction(out)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn is_feasible(constraints: &[Constraint<usize, usize>], subset: &[usize]) -> bool {
+        let (sys, _) = DCS::from_scratch(subset.iter().map(|i| constraints[*i].clone()));
+        sys.is_feasible()
+    }
+
+    fn brute_force(
+        constraints: &[Constraint<usize, usize>],
+    ) -> (HashSet<Vec<usize>>, HashSet<Vec<usize>>) {
+        let n = constraints.len();
+        let mut muses = HashSet::new();
+        let mut mcses = HashSet::new();
+        for mask in 0..(1usize << n) {
+            let subset: Vec<usize> = (0..n).filter(|i| mask & (1 << i) != 0).collect();
+            let complement: Vec<usize> = (0..n).filter(|i| mask & (1 << i) == 0).collect();
+            let without = |set: &[usize], i: usize| -> Vec<usize> {
+                set.iter().filter(|j| **j != i).cloned().collect()
+            };
+            if !is_feasible(constraints, &subset)
+                && subset
+                    .iter()
+                    .all(|i| is_feasible(constraints, &without(&subset, *i)))
+            {
+                muses.insert(subset.clone());
+            }
+            if is_feasible(constraints, &complement)
+                && subset.iter().all(|i| {
+                    let mut larger = complement.clone();
+                    larger.push(*i);
+                    !is_feasible(constraints, &larger)
+                })
+            {
+                mcses.insert(subset);
+            }
+        }
+        (muses, mcses)
+    }
+
+    #[test]
+    fn test_marco_matches_brute_force() {
+        for seed in 0..30 {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            let num_vars = rng.gen_range(2..5);
+            let num_constraints = rng.gen_range(1..8);
+            let constraints: Vec<Constraint<usize, usize>> = (0..num_constraints)
+                .map(|tag| {
+                    Constraint::new(
+                        rng.gen_range(0..num_vars),
+                        rng.gen_range(0..num_vars),
+                        rng.gen_range(-5..5),
+                        tag,
+                    )
+                })
+                .collect();
+            let (expected_muses, expected_mcses) = brute_force(&constraints);
+            let mut muses = HashSet::new();
+            let mut mcses = HashSet::new();
+            for subset in Marco::new(constraints.clone()) {
+                match subset.unwrap() {
+                    MinimalSubset::Unsatisfiable(mut tags) => {
+                        tags.sort();
+                        assert!(muses.insert(tags));
+                    }
+                    MinimalSubset::Correction(mut tags) => {
+                        tags.sort();
+                        assert!(mcses.insert(tags));
+                    }
+                }
+            }
+            assert_eq!(muses, expected_muses);
+            assert_eq!(mcses, expected_mcses);
+        }
+    }
+
+    #[test]
+    fn test_marco_overflow() {
+        let constraints = vec![
+            Constraint::new(1, 0, i64::MIN, 0),
+            Constraint::new(2, 1, -1, 1),
+        ];
+        let subsets: Vec<_> = Marco::new(constraints).collect();
+        assert_eq!(subsets, vec![Err(DcsError::Overflow)]);
+    }
+}
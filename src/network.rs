@@ -0,0 +1,211 @@
+use crate::{ConstraintTag, DcsResult, Solution, VarId, Weight, DCS};
+use std::collections::HashMap;
+
+// the tightest bound the system implies on the difference of every pair of variables.
+// bounds[y][x] is the smallest c such that x - y <= c is implied, and is missing if x - y is unbounded from above.
+pub struct MinimalNetwork<T: VarId, W: Weight = i64> {
+    bounds: HashMap<T, HashMap<T, W>>,
+}
+
+impl<T: VarId, W: Weight> MinimalNetwork<T, W> {
+    pub fn get_implied_ub(&self, x: &T, y: &T) -> Option<W> {
+        // x - y <= c. None if x - y is unbounded from above, or if x or y are not in the network.
+        self.bounds.get(y)?.get(x).cloned()
+    }
+    pub fn implied_ubs_from(&self, y: &T) -> HashMap<T, W> {
+        // x -> c for every bounded x - y <= c.
+        self.bounds.get(y).cloned().unwrap_or_default()
+    }
+    pub fn implied_ubs_to(&self, x: &T) -> HashMap<T, W> {
+        // y -> c for every bounded x - y <= c.
+        self.bounds
+            .iter()
+            .filter_map(|(y, from_y)| Some((y.clone(), *from_y.get(x)?)))
+            .collect()
+    }
+    pub fn contains_var(&self, var: &T) -> bool {
+        self.bounds.contains_key(var)
+    }
+    pub fn variables(&self) -> impl Iterator<Item = &T> + '_ {
+        self.bounds.keys()
+    }
+    pub fn len(&self) -> usize {
+        self.bounds.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.bounds.is_empty()
+    }
+    pub(crate) fn add_edge(&mut self, u: &T, v: &T, w: W) -> Option<()> {
+        // tightens the bounds after the edge u -> v (i.e. v - u <= w) was added. every path that got shorter
+        // goes y ~> u -> v ~> x. None if a new bound overflows, in which case no bound is changed.
+        for var in [u, v] {
+            self.bounds
+                .entry(var.clone())
+                .or_insert_with(|| HashMap::from([(var.clone(), W::zero())]));
+        }
+        if self.bounds[u].get(v).is_some_and(|u2v| *u2v <= w) {
+            return Some(());
+        }
+        let from_v: Vec<(T, W)> = self.bounds[v]
+            .iter()
+            .map(|(x, d)| (x.clone(), *d))
+            .collect();
+        let mut updates = vec![];
+        for (y, from_y) in self.bounds.iter() {
+            let Some(y2u) = from_y.get(u) else {
+                continue;
+            };
+            let y2v = y2u.checked_add(&w)?;
+            for (x, v2x) in from_v.iter() {
+                let y2x = y2v.checked_add(v2x)?;
+                if from_y.get(x).is_none_or(|old| y2x < *old) {
+                    updates.push((y.clone(), x.clone(), y2x));
+                }
+            }
+        }
+        for (y, x, y2x) in updates {
+            self.bounds.get_mut(&y).unwrap().insert(x, y2x);
+        }
+        Some(())
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&T, &T, W)> + '_ {
+        // every (x, y, c) such that x - y <= c is the tightest implied bound.
+        self.bounds
+            .iter()
+            .flat_map(|(y, from_y)| from_y.iter().map(move |(x, c)| (x, y, *c)))
+    }
+}
+
+impl<T: VarId, C: ConstraintTag, W: Weight> DCS<T, C, W> {
+    pub fn minimal_network(
+        &self,
+        sol: &Solution<T, W>,
+    ) -> DcsResult<MinimalNetwork<T, W>, T, C, W> {
+        // johnson's algorithm: sol is a feasible potential, so the scaled edge weights are non-negative,
+        // and a dijkstra from every variable gives all the shortest paths.
+        // like get_implied_ub, only the feasible constraints are taken into account.
+        let bounds = self
+            .feasible_constraints
+            .variables()
+            .into_iter()
+            .map(|y| Ok((y.clone(), self.distances_from(y, sol)?)))
+            .collect::<DcsResult<_, T, C, W>>()?;
+        Ok(MinimalNetwork { bounds })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Constraint, DcsError};
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn test_matches_implied_ub() {
+        for seed in 0..20 {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            let mut sys = DCS::new();
+            let mut sol = Solution::new();
+            for tag in 0..rng.gen_range(1..20) {
+                let constraint = Constraint {
+                    v: rng.gen_range(0..8),
+                    u: rng.gen_range(0..8),
+                    c: rng.gen_range(-10..30),
+                    strict: rng.gen_bool(0.2),
+                    tag,
+                };
+                match sys.add_constraint(constraint, &sol).1 {
+                    Ok(new_sol) => sol = new_sol,
+                    Err(DcsError::Infeasible(_)) => {}
+                    Err(err) => panic!("{}", err),
+                }
+            }
+            let network = sys.minimal_network(&sol).unwrap();
+            for x in 0..8 {
+                for y in 0..8 {
+                    if !network.contains_var(&x) || !network.contains_var(&y) {
+                        // only in infeasible constraints, or in none.
+                        assert_eq!(network.get_implied_ub(&x, &y), None);
+                        continue;
+                    }
+                    let expected = sys.get_implied_ub(&x, &y, &sol).unwrap();
+                    assert_eq!(network.get_implied_ub(&x, &y), expected);
+                }
+            }
+            assert!(network
+                .iter()
+                .all(|(x, y, c)| network.get_implied_ub(x, y) == Some(c)));
+        }
+    }
+
+    #[test]
+    fn test_incremental_network() {
+        for seed in 0..20 {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            let mut sys = DCS::new();
+            let mut sol = Solution::new();
+            sys.maintain_network(&sol).unwrap();
+            let mut ids = vec![];
+            for tag in 0..40 {
+                match rng.gen_range(0..11) {
+                    0 if !ids.is_empty() => {
+                        let id = ids.swap_remove(rng.gen_range(0..ids.len()));
+                        sol = sys.remove_by_id(id, &sol).unwrap();
+                    }
+                    1 if !ids.is_empty() => {
+                        let id = ids[rng.gen_range(0..ids.len())];
+                        let c = sys.get(id).unwrap().c + rng.gen_range(-10..10);
+                        let _ = sys.update_bound(id, c, &mut sol);
+                    }
+                    2 if ids.len() > 1 => {
+                        let batch: Vec<_> = ids
+                            .choose_multiple(&mut rng, 3)
+                            .map(|id| sys.get(*id).unwrap().clone())
+                            .collect();
+                        sol = sys.remove_constraints(batch.into_iter(), &sol).unwrap();
+                        ids.retain(|id| sys.get(*id).is_some());
+                    }
+                    3 => sys.push(),
+                    4 => {
+                        sys.pop(&mut sol);
+                        ids.retain(|id| sys.get(*id).is_some());
+                    }
+                    _ => {
+                        let constraint = Constraint::new(
+                            rng.gen_range(0..8),
+                            rng.gen_range(0..8),
+                            rng.gen_range(-10..30),
+                            tag,
+                        );
+                        let (id, result) = sys.add_constraint(constraint, &sol);
+                        ids.push(id);
+                        if let Ok(new_sol) = result {
+                            sol = new_sol;
+                        }
+                    }
+                }
+                let expected = sys.minimal_network(&sol).unwrap();
+                let network = sys.network().unwrap();
+                assert_eq!(network.len(), expected.len());
+                for (x, y, c) in expected.iter() {
+                    assert_eq!(network.get_implied_ub(x, y), Some(c));
+                    assert_eq!(sys.get_implied_ub(x, y, &sol), Ok(Some(c)));
+                }
+                assert_eq!(network.iter().count(), expected.iter().count());
+            }
+        }
+
+        let mut sys = DCS::new();
+        let mut sol = Solution::new();
+        sys.maintain_network(&sol).unwrap();
+        sys.drop_network();
+        assert!(sys.network().is_none());
+        sol = sys
+            .add_constraint(Constraint::new(1, 0, 3, ()), &sol)
+            .1
+            .unwrap();
+        assert!(sys.network().is_none());
+        assert_eq!(sys.get_implied_ub(&1, &0, &sol), Ok(Some(3)));
+    }
+}
@@ -0,0 +1,166 @@
+use crate::{
+    Constraint, ConstraintId, ConstraintTag, DcsError, DcsResult, Solution, VarId, Weight, DCS,
+};
+use std::fmt::Display;
+
+// a variable of a system with unary bounds. the origin stands for 0, so x <= c is x - origin <= c.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum Var<T: VarId> {
+    Origin,
+    Id(T),
+}
+
+impl<T: VarId> Display for Var<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Var::Origin => write!(f, "origin"),
+            Var::Id(var) => write!(f, "{}", var),
+        }
+    }
+}
+
+impl<T: VarId> From<T> for Var<T> {
+    fn from(var: T) -> Self {
+        Var::Id(var)
+    }
+}
+
+impl<T: VarId, C: ConstraintTag, W: Weight> Constraint<Var<T>, C, W> {
+    pub fn upper_bound(x: T, c: W, tag: C) -> Self {
+        // x <= c
+        Constraint::new(Var::Id(x), Var::Origin, c, tag)
+    }
+    pub fn lower_bound(x: T, c: W, tag: C) -> Option<Self> {
+        // x >= c, i.e. origin - x <= -c. None if -c overflows.
+        Some(Constraint::new(
+            Var::Origin,
+            Var::Id(x),
+            W::zero().checked_sub(&c)?,
+            tag,
+        ))
+    }
+}
+
+// the handle of the new bound, and the updated solution, as returned by add_constraint.
+type Added<T, C, W> = (ConstraintId, DcsResult<Solution<Var<T>, W>, Var<T>, C, W>);
+
+impl<T: VarId, C: ConstraintTag, W: Weight> DCS<Var<T>, C, W> {
+    // both bounds fail, and add nothing, if the bound can't be represented. otherwise they behave like
+    // add_constraint, and the bound is kept even if it is infeasible.
+    pub fn add_upper_bound(
+        &mut self,
+        x: T,
+        c: W,
+        tag: C,
+        sol: &Solution<Var<T>, W>,
+    ) -> DcsResult<Added<T, C, W>, Var<T>, C, W> {
+        // x - origin <= c can always be represented.
+        Ok(self.add_constraint(Constraint::upper_bound(x, c, tag), sol))
+    }
+    pub fn add_lower_bound(
+        &mut self,
+        x: T,
+        c: W,
+        tag: C,
+        sol: &Solution<Var<T>, W>,
+    ) -> DcsResult<Added<T, C, W>, Var<T>, C, W> {
+        // origin - x <= -c, which can't be represented if -c overflows.
+        let constraint = Constraint::lower_bound(x, c, tag).ok_or(DcsError::Overflow)?;
+        Ok(self.add_constraint(constraint, sol))
+    }
+    pub fn get_implied_upper_bound(
+        &self,
+        x: &T,
+        sol: &Solution<Var<T>, W>,
+    ) -> DcsResult<Option<W>, Var<T>, C, W> {
+        self.get_implied_ub(&Var::Id(x.clone()), &Var::Origin, sol)
+    }
+    pub fn get_implied_lower_bound(
+        &self,
+        x: &T,
+        sol: &Solution<Var<T>, W>,
+    ) -> DcsResult<Option<W>, Var<T>, C, W> {
+        match self.get_implied_ub(&Var::Origin, &Var::Id(x.clone()), sol)? {
+            Some(ub) => Ok(Some(W::zero().checked_sub(&ub).ok_or(DcsError::Overflow)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+// a solution stays a solution when every value is shifted by the same amount, and the system doesn't keep
+// the origin at 0 in the raw values that `get` and `iter` return. `value` and `values` are relative to the
+// origin, so it reads 0 in them, and they are the ones to read bounded variables with.
+// `normalize(Some(&Var::Origin))` shifts the raw values so that the origin reads 0 in them as well.
+impl<T: VarId, W: Weight> Solution<Var<T>, W> {
+    pub fn value(&self, x: &T) -> Option<W> {
+        // None if x is unknown, or if its value relative to the origin overflows.
+        let x = *self.get(&Var::Id(x.clone()))?;
+        x.checked_sub(&self.get_or(&Var::Origin, W::zero()))
+    }
+    pub fn values(&self) -> impl Iterator<Item = (&T, Option<W>)> + '_ {
+        // every variable but the origin, with its value as given by `value`.
+        let origin = self.get_or(&Var::Origin, W::zero());
+        self.iter().filter_map(move |(var, x)| match var {
+            Var::Origin => None,
+            Var::Id(var) => Some((var, x.checked_sub(&origin))),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unary_bounds() {
+        let mut sys = DCS::new();
+        let mut sol = Solution::new();
+        sol = sys
+            .add_lower_bound("start", 10, 0, &sol)
+            .unwrap()
+            .1
+            .unwrap();
+        sol = sys.add_upper_bound("end", 30, 1, &sol).unwrap().1.unwrap();
+        // end - start >= 15
+        let duration = Constraint::new(Var::Id("start"), Var::Id("end"), -15, 2);
+        sol = sys.add_constraint(duration, &sol).1.unwrap();
+        assert!(sys.check_solution(&sol));
+        let start = sol.value(&"start").unwrap();
+        let end = sol.value(&"end").unwrap();
+        assert!((10..=15).contains(&start) && end <= 30 && end - start >= 15);
+        assert_eq!(sys.get_implied_upper_bound(&"start", &sol), Ok(Some(15)));
+        assert_eq!(sys.get_implied_lower_bound(&"end", &sol), Ok(Some(25)));
+        assert_eq!(sys.get_implied_lower_bound(&"start", &sol), Ok(Some(10)));
+        let mut values: Vec<(&str, Option<i64>)> = sol.values().map(|(var, x)| (*var, x)).collect();
+        values.sort();
+        assert_eq!(values, vec![("end", Some(end)), ("start", Some(start))]);
+
+        // the raw values are shifted, the relative ones aren't.
+        let mut shifted: Solution<Var<&str>, i64> =
+            sol.iter().map(|(var, x)| (var.clone(), x + 7)).collect();
+        assert_eq!(
+            shifted.get(&Var::Origin),
+            Some(&(sol.get_or(&Var::Origin, 0) + 7))
+        );
+        assert_eq!(shifted.value(&"start"), Some(start));
+        shifted.normalize(Some(&Var::Origin)).unwrap();
+        assert_eq!(shifted.get(&Var::Origin), Some(&0));
+        assert_eq!(shifted.get(&Var::Id("end")), Some(&end));
+
+        let (_, result) = sys.add_upper_bound("end", 24, 3, &sol).unwrap();
+        let Err(DcsError::Infeasible(cycle)) = result else {
+            panic!("expected a negative cycle");
+        };
+        let mut tags: Vec<i32> = cycle.tags().cloned().collect();
+        tags.sort();
+        assert_eq!(tags, vec![0, 2, 3]);
+
+        let num_constraints = sys.constraints().count();
+        assert_eq!(
+            sys.add_lower_bound("end", i64::MIN, 4, &sol).unwrap_err(),
+            DcsError::Overflow
+        );
+        assert_eq!(sys.constraints().count(), num_constraints);
+        assert_eq!(sol.value(&"zzz"), None);
+    }
+}
@@ -0,0 +1,76 @@
+use crate::Weight;
+use num_rational::Ratio;
+use num_traits::{CheckedAdd, CheckedSub, Zero};
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+
+// exact rational bounds, e.g. Ratio<i64>. there is no largest rational below a bound,
+// so strict constraints need `delta::Delta<Ratio<_>>`.
+impl<I> Weight for Ratio<I> where
+    Ratio<I>: Copy + Ord + Hash + Debug + Display + Zero + CheckedAdd + CheckedSub
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::delta::Delta;
+    use crate::theory::{DifferenceLogic, TheoryError};
+    use crate::{Constraint, DcsError, Solution, DCS};
+
+    #[test]
+    fn test_rational_weights() {
+        let r = |n, d| Ratio::new(n, d);
+        // 1/3 + 1/3 + 1/3 is exactly 1, so closing the cycle with -1 leaves a zero weight cycle.
+        let mut sys = DCS::new();
+        let mut sol = Solution::new();
+        for (v, u, c) in [
+            (1, 0, r(1, 3)),
+            (2, 1, r(1, 3)),
+            (3, 2, r(1, 3)),
+            (0, 3, r(-1, 1)),
+        ] {
+            sol = sys
+                .add_constraint(Constraint::new(v, u, c, ()), &sol)
+                .1
+                .unwrap();
+        }
+        assert!(sys.is_feasible());
+        assert!(sys.check_solution(&sol));
+        assert_eq!(sys.get_implied_ub(&2, &0, &sol), Ok(Some(r(2, 3))));
+        assert_eq!(sys.get_implied_ub(&0, &2, &sol), Ok(Some(r(-2, 3))));
+        let (_, result) = sys.add_constraint(Constraint::new(0, 2, r(-7, 10), ()), &sol);
+        let Err(DcsError::Infeasible(cycle)) = result else {
+            panic!("expected a negative cycle");
+        };
+        assert_eq!(cycle.weight, r(-1, 30));
+        let (_, result) = sys.add_constraint(Constraint::strict(4, 0, r(1, 2), ()), &sol);
+        assert_eq!(result.unwrap_err(), DcsError::StrictUnsupported);
+        assert!(!Ratio::<i64>::supports_strict());
+        // negating a non-strict atom makes it strict.
+        let mut theory = DifferenceLogic::new();
+        assert_eq!(
+            theory.assert_literal(Constraint::new(1, 0, r(1, 2), 0), false),
+            Err(TheoryError::StrictUnsupported)
+        );
+        assert!(!theory.is_assigned(&0));
+
+        // strict constraints over the rationals go through Delta.
+        let mut sys = DCS::new();
+        let mut sol = Solution::new();
+        let d = |n, d| Delta::from(r(n, d));
+        for constraint in [
+            Constraint::strict(1, 0, d(1, 2), ()),
+            Constraint::strict(0, 1, d(-1, 3), ()),
+        ] {
+            sol = sys.add_constraint(constraint, &sol).1.unwrap();
+        }
+        assert!(sys.check_solution(&sol));
+        assert_eq!(
+            sys.get_implied_ub(&1, &0, &sol),
+            Ok(Some(Delta::new(r(1, 2), 1)))
+        );
+        let (_, result) = sys.add_constraint(Constraint::new(0, 1, d(-1, 2), ()), &sol);
+        assert!(matches!(result, Err(DcsError::Infeasible(_))));
+    }
+}
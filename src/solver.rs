@@ -0,0 +1,220 @@
+use crate::{
+    Constraint, ConstraintId, ConstraintTag, DcsResult, IntervalId, Path, Solution, SolutionDelta,
+    Status, VarId, WatchEvent, Weight, DCS,
+};
+use std::collections::HashMap;
+
+// a system that keeps its own solution, so there is no way to pass it a stale one.
+// the solution is updated in place, and operations return what they changed.
+// read only queries that don't need the solution go through `system`.
+pub struct Solver<T: VarId, C: ConstraintTag, W: Weight = i64> {
+    sys: DCS<T, C, W>,
+    sol: Solution<T, W>,
+}
+
+impl<T: VarId, C: ConstraintTag, W: Weight> Solver<T, C, W> {
+    pub fn new() -> Self {
+        Solver {
+            sys: DCS::new(),
+            sol: Solution::new(),
+        }
+    }
+    pub fn with_tolerance(tolerance: W) -> Self {
+        Solver {
+            sys: DCS::with_tolerance(tolerance),
+            sol: Solution::new(),
+        }
+    }
+    pub fn from_scratch<It>(constraints: It) -> Self
+    where
+        It: Iterator<Item = Constraint<T, C, W>>,
+    {
+        let (sys, sol) = DCS::from_scratch(constraints);
+        Solver { sys, sol }
+    }
+    pub fn from_scratch_with_tolerance<It>(constraints: It, tolerance: W) -> Self
+    where
+        It: Iterator<Item = Constraint<T, C, W>>,
+    {
+        let (sys, sol) = DCS::from_scratch_with_tolerance(constraints, tolerance);
+        Solver { sys, sol }
+    }
+    pub fn from_scratch_batch<It>(constraints: It) -> Self
+    where
+        It: Iterator<Item = Constraint<T, C, W>>,
+    {
+        let (sys, sol) = DCS::from_scratch_batch(constraints);
+        Solver { sys, sol }
+    }
+    pub fn from_scratch_batch_with_tolerance<It>(constraints: It, tolerance: W) -> Self
+    where
+        It: Iterator<Item = Constraint<T, C, W>>,
+    {
+        let (sys, sol) = DCS::from_scratch_batch_with_tolerance(constraints, tolerance);
+        Solver { sys, sol }
+    }
+    pub fn solution(&self) -> &Solution<T, W> {
+        &self.sol
+    }
+    pub fn system(&self) -> &DCS<T, C, W> {
+        &self.sys
+    }
+    pub fn into_parts(self) -> (DCS<T, C, W>, Solution<T, W>) {
+        (self.sys, self.sol)
+    }
+    pub fn add_constraint(
+        &mut self,
+        constraint: Constraint<T, C, W>,
+    ) -> (ConstraintId, DcsResult<SolutionDelta<T, W>, T, C, W>) {
+        self.sys.add_constraint_in_place(constraint, &mut self.sol)
+    }
+    pub fn add(&mut self, constraint: Constraint<T, C, W>) -> ConstraintId {
+        self.sys.add(constraint)
+    }
+    pub fn solve(&mut self) -> Status {
+        self.sys.solve(&mut self.sol)
+    }
+    pub fn add_interval(
+        &mut self,
+        v: T,
+        u: T,
+        lo: W,
+        hi: W,
+        tag: C,
+    ) -> DcsResult<(IntervalId, SolutionDelta<T, W>), T, C, W> {
+        self.sys
+            .add_interval_in_place(v, u, lo, hi, tag, &mut self.sol)
+    }
+    pub fn add_equality(
+        &mut self,
+        v: T,
+        u: T,
+        k: W,
+        tag: C,
+    ) -> DcsResult<(IntervalId, SolutionDelta<T, W>), T, C, W> {
+        self.add_interval(v, u, k, k, tag)
+    }
+    pub fn remove_interval(&mut self, id: IntervalId) -> DcsResult<SolutionDelta<T, W>, T, C, W> {
+        self.sys.remove_interval_in_place(id, &mut self.sol)
+    }
+    pub fn remove_constraint(
+        &mut self,
+        constraint: Constraint<T, C, W>,
+    ) -> DcsResult<SolutionDelta<T, W>, T, C, W> {
+        self.sys
+            .remove_constraint_in_place(constraint, &mut self.sol)
+    }
+    pub fn remove_by_id(&mut self, id: ConstraintId) -> DcsResult<SolutionDelta<T, W>, T, C, W> {
+        self.sys.remove_by_id_in_place(id, &mut self.sol)
+    }
+    pub fn remove_constraints<I: Iterator<Item = Constraint<T, C, W>>>(
+        &mut self,
+        constraints: I,
+    ) -> DcsResult<SolutionDelta<T, W>, T, C, W> {
+        self.sys
+            .remove_constraints_in_place(constraints, &mut self.sol)
+    }
+    pub fn update_bound(
+        &mut self,
+        id: ConstraintId,
+        new_c: W,
+    ) -> DcsResult<SolutionDelta<T, W>, T, C, W> {
+        self.sys.update_bound(id, new_c, &mut self.sol)
+    }
+    pub fn tighten(
+        &mut self,
+        id: ConstraintId,
+        new_c: W,
+    ) -> DcsResult<SolutionDelta<T, W>, T, C, W> {
+        self.sys.tighten(id, new_c, &mut self.sol)
+    }
+    pub fn relax(&mut self, id: ConstraintId, new_c: W) -> DcsResult<SolutionDelta<T, W>, T, C, W> {
+        self.sys.relax(id, new_c, &mut self.sol)
+    }
+    pub fn get_implied_ub(&self, x: &T, y: &T) -> DcsResult<Option<W>, T, C, W> {
+        self.sys.get_implied_ub(x, y, &self.sol)
+    }
+    pub fn explain_implied_ub(&self, x: &T, y: &T) -> DcsResult<Option<Path<T, C, W>>, T, C, W> {
+        self.sys.explain_implied_ub(x, y, &self.sol)
+    }
+    pub fn implied_ubs_from(&self, y: &T) -> DcsResult<HashMap<T, W>, T, C, W> {
+        self.sys.implied_ubs_from(y, &self.sol)
+    }
+    pub fn implied_ubs_to(&self, x: &T) -> DcsResult<HashMap<T, W>, T, C, W> {
+        self.sys.implied_ubs_to(x, &self.sol)
+    }
+    pub fn get_implied_lb(&self, x: &T, y: &T) -> DcsResult<Option<W>, T, C, W> {
+        self.sys.get_implied_lb(x, y, &self.sol)
+    }
+    pub fn maintain_network(&mut self) -> DcsResult<(), T, C, W> {
+        self.sys.maintain_network(&self.sol)
+    }
+    pub fn minimal_conflicts(&self) -> Vec<(C, Vec<C>)> {
+        self.sys.minimal_conflicts(&self.sol)
+    }
+    pub fn watch(&mut self, constraint: Constraint<T, C, W>) -> DcsResult<(), T, C, W> {
+        self.sys.watch(constraint, &self.sol)
+    }
+    pub fn take_watch_events(&mut self) -> Vec<WatchEvent<T, C, W>> {
+        self.sys.take_watch_events()
+    }
+    pub fn push(&mut self) {
+        self.sys.push();
+    }
+    pub fn pop(&mut self) -> bool {
+        self.sys.pop(&mut self.sol)
+    }
+}
+
+impl<T: VarId, C: ConstraintTag, W: Weight> Default for Solver<T, C, W> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DcsError;
+
+    #[test]
+    fn test_owned_solution() {
+        let mut solver = Solver::new();
+        solver
+            .add_constraint(Constraint::new("y", "x", -2, ()))
+            .1
+            .unwrap();
+        let (id, result) = solver.add_constraint(Constraint::new("z", "y", -3, ()));
+        result.unwrap();
+        assert!(solver.system().check_solution(solver.solution()));
+        assert_eq!(solver.get_implied_ub(&"z", &"x"), Ok(Some(-5)));
+
+        // a rejected constraint leaves the solution as it was.
+        let before = solver.solution().clone();
+        let (_, result) = solver.add_constraint(Constraint::new("x", "z", 4, ()));
+        assert!(matches!(result, Err(DcsError::Infeasible(_))));
+        assert!(!solver.system().is_feasible());
+        for var in ["x", "y", "z"] {
+            assert_eq!(solver.solution().get_or(&var, 0), before.get_or(&var, 0));
+        }
+        solver
+            .remove_constraint(Constraint::new("x", "z", 4, ()))
+            .unwrap();
+        assert!(solver.system().is_feasible());
+
+        solver.push();
+        solver.tighten(id, -10).unwrap();
+        assert_eq!(solver.get_implied_ub(&"z", &"x"), Ok(Some(-12)));
+        assert!(solver.system().check_solution(solver.solution()));
+        assert!(solver.pop());
+        assert_eq!(solver.get_implied_ub(&"z", &"x"), Ok(Some(-5)));
+        assert!(solver.system().check_solution(solver.solution()));
+
+        let (interval, _) = solver.add_equality("w", "z", 1, ()).unwrap();
+        assert_eq!(solver.get_implied_ub(&"w", &"x"), Ok(Some(-4)));
+        solver.remove_interval(interval).unwrap();
+        let (sys, sol) = solver.into_parts();
+        assert!(sys.check_solution(&sol));
+        assert_eq!(sys.constraints().count(), 2);
+    }
+}
@@ -0,0 +1,310 @@
+use crate::{Constraint, ConstraintTag, DcsError, Solution, VarId, WatchEvent, Weight, DCS};
+use std::collections::HashMap;
+use std::fmt::Display;
+
+// an atom and its value: (tag, true) stands for the atom's constraint, and (tag, false) for its negation.
+pub type Literal<C> = (C, bool);
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum TheoryError<C: ConstraintTag> {
+    // literals that can't hold together, including the asserted one.
+    Conflict(Vec<Literal<C>>),
+    // the literal can't be checked without overflow. nothing is asserted.
+    Overflow,
+    // the literal is strict, and the weight type has no `strictly_below`. nothing is asserted.
+    StrictUnsupported,
+}
+
+impl<C: ConstraintTag> Display for TheoryError<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TheoryError::Conflict(literals) => write!(f, "conflict: {:?}", literals),
+            TheoryError::Overflow => write!(f, "arithmetic overflow"),
+            TheoryError::StrictUnsupported => {
+                write!(
+                    f,
+                    "strict constraints are not supported by this weight type"
+                )
+            }
+        }
+    }
+}
+
+impl<C: ConstraintTag> std::error::Error for TheoryError<C> {}
+
+impl<T: VarId, C: ConstraintTag, W: Weight> From<DcsError<T, Literal<C>, W>> for TheoryError<C> {
+    fn from(err: DcsError<T, Literal<C>, W>) -> Self {
+        match err {
+            DcsError::Infeasible(cycle) => TheoryError::Conflict(cycle.tags().cloned().collect()),
+            DcsError::StrictUnsupported => TheoryError::StrictUnsupported,
+            _ => TheoryError::Overflow,
+        }
+    }
+}
+
+// difference logic as a theory solver for a DPLL(T) loop.
+// every constraint is an atom, identified by its tag, that can be asserted either way.
+pub struct DifferenceLogic<T: VarId, C: ConstraintTag, W: Weight = i64> {
+    // the constraints of the asserted literals, tagged with them.
+    sys: DCS<T, Literal<C>, W>,
+    sol: Solution<T, W>,
+    // asserted and propagated literals, with the level they were assigned at (in assignment order).
+    assigned: Vec<(Literal<C>, usize)>,
+    values: HashMap<C, bool>,
+    // for propagated atoms, the asserted literals that imply them.
+    explanations: HashMap<C, Vec<Literal<C>>>,
+}
+
+impl<T: VarId, C: ConstraintTag, W: Weight> DifferenceLogic<T, C, W> {
+    pub fn new() -> Self {
+        DifferenceLogic {
+            sys: DCS::new(),
+            sol: Solution::new(),
+            assigned: vec![],
+            values: HashMap::new(),
+            explanations: HashMap::new(),
+        }
+    }
+    fn literal(atom: Constraint<T, C, W>, value: bool) -> Option<Constraint<T, Literal<C>, W>> {
+        // the constraint that holds if the atom has the given value. not v - u <= c is u - v < -c,
+        // and not v - u < c is u - v <= -c. None if -c overflows.
+        let Constraint {
+            v,
+            u,
+            c,
+            strict,
+            tag,
+        } = atom;
+        if value {
+            return Some(Constraint {
+                v,
+                u,
+                c,
+                strict,
+                tag: (tag, true),
+            });
+        }
+        Some(Constraint {
+            v: u,
+            u: v,
+            c: W::zero().checked_sub(&c)?,
+            strict: !strict,
+            tag: (tag, false),
+        })
+    }
+    pub fn register_atom(&mut self, atom: Constraint<T, C, W>) -> Result<(), TheoryError<C>> {
+        // atoms the sat solver knows about are watched for theory propagation.
+        // they should be registered at level 0, since backtracking past a level forgets the atoms registered in it.
+        let atom = Self::literal(atom, true).ok_or(TheoryError::Overflow)?;
+        self.sys.watch(atom, &self.sol).map_err(TheoryError::from)
+    }
+    pub fn level(&self) -> usize {
+        self.sys.num_scopes()
+    }
+    pub fn new_level(&mut self) {
+        self.sys.push();
+    }
+    pub fn backtrack_to(&mut self, level: usize) {
+        while self.level() > level {
+            self.sys.pop(&mut self.sol);
+        }
+        while let Some(((atom, _), literal_level)) = self.assigned.last() {
+            if *literal_level <= level {
+                break;
+            }
+            self.values.remove(atom);
+            self.explanations.remove(atom);
+            self.assigned.pop();
+        }
+    }
+    pub fn is_assigned(&self, atom: &C) -> bool {
+        self.values.contains_key(atom)
+    }
+    pub fn value(&self, atom: &C) -> Option<bool> {
+        self.values.get(atom).cloned()
+    }
+    pub fn solution(&self) -> &Solution<T, W> {
+        &self.sol
+    }
+    pub fn explain(&self, atom: &C) -> Option<&[Literal<C>]> {
+        self.explanations.get(atom).map(|e| e.as_slice())
+    }
+    pub fn assert_literal(
+        &mut self,
+        atom: Constraint<T, C, W>,
+        value: bool,
+    ) -> Result<Vec<Literal<C>>, TheoryError<C>> {
+        // asserts the atom, or its negation if value is false.
+        // on success, returns the registered atoms that became true or false, and is ready for the next assertion.
+        // on error, nothing is asserted.
+        let constraint = Self::literal(atom, value).ok_or(TheoryError::Overflow)?;
+        let literal = constraint.tag.clone();
+        let (id, result) = self.sys.add_constraint_in_place(constraint, &mut self.sol);
+        if let Err(err) = result {
+            // the failed constraint is kept by the system, and the handle was just added, so removing it can't fail.
+            let _ = self.sys.remove_by_id_in_place(id, &mut self.sol);
+            return Err(err.into());
+        }
+        self.assign(literal, None);
+        Ok(self.propagate())
+    }
+    fn assign(&mut self, literal: Literal<C>, explanation: Option<Vec<Literal<C>>>) {
+        let level = self.level();
+        let (atom, value) = literal.clone();
+        if self.values.contains_key(&atom) {
+            return;
+        }
+        self.values.insert(atom.clone(), value);
+        self.assigned.push((literal, level));
+        if let Some(explanation) = explanation {
+            self.explanations.insert(atom, explanation);
+        }
+    }
+    fn propagate(&mut self) -> Vec<Literal<C>> {
+        let mut out = vec![];
+        for event in self.sys.take_watch_events() {
+            // atom.v - atom.u <= atom.c is implied by a tight enough path from u to v,
+            // and is impossible if a path from v to u closes a negative cycle with it.
+            let (atom, value, path) = match event {
+                WatchEvent::Implied(atom) => {
+                    let path = self.sys.shortest_path(&atom.u, &atom.v, &self.sol);
+                    (atom, true, path)
+                }
+                WatchEvent::Impossible(atom) => {
+                    let path = self.sys.shortest_path(&atom.v, &atom.u, &self.sol);
+                    (atom, false, path)
+                }
+            };
+            let (atom, _) = atom.tag;
+            if self.is_assigned(&atom) {
+                continue;
+            }
+            // a path that overflows can't explain anything, so the atom is left for the sat solver to decide.
+            let Ok(path) = path else {
+                continue;
+            };
+            let explanation = path
+                .unwrap_or_default()
+                .into_iter()
+                .map(|c| c.tag)
+                .collect();
+            self.assign((atom.clone(), value), Some(explanation));
+            out.push((atom, value));
+        }
+        out
+    }
+}
+
+impl<T: VarId, C: ConstraintTag, W: Weight> Default for DifferenceLogic<T, C, W> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assert_propagate_backtrack() {
+        let mut theory = DifferenceLogic::new();
+        theory
+            .register_atom(Constraint::new("x", "y", 5, 10))
+            .unwrap();
+        theory
+            .register_atom(Constraint::new("y", "x", -3, 11))
+            .unwrap();
+        theory
+            .register_atom(Constraint::new("x", "y", 1, 12))
+            .unwrap();
+
+        assert_eq!(
+            theory.assert_literal(Constraint::new("x", "z", 1, 1), true),
+            Ok(vec![])
+        );
+        theory.new_level();
+        let mut propagated = theory
+            .assert_literal(Constraint::new("z", "y", 1, 2), true)
+            .unwrap();
+        propagated.sort();
+        // x - y <= 2 now holds, which makes atom 10 true and atom 11 false. atom 12 stays open.
+        assert_eq!(propagated, vec![(10, true), (11, false)]);
+        let mut explanation = theory.explain(&10).unwrap().to_vec();
+        explanation.sort();
+        assert_eq!(explanation, vec![(1, true), (2, true)]);
+        assert_eq!(theory.value(&11), Some(false));
+
+        theory.new_level();
+        let Err(TheoryError::Conflict(mut conflict)) =
+            theory.assert_literal(Constraint::new("y", "x", -3, 3), true)
+        else {
+            panic!("expected a conflict");
+        };
+        conflict.sort();
+        assert_eq!(conflict, vec![(1, true), (2, true), (3, true)]);
+        assert_eq!(theory.level(), 2);
+
+        theory.backtrack_to(1);
+        assert!(theory.is_assigned(&10));
+        theory.backtrack_to(0);
+        assert!(!theory.is_assigned(&10));
+        assert!(!theory.is_assigned(&2));
+        assert!(theory.explain(&10).is_none());
+        let mut propagated = theory
+            .assert_literal(Constraint::new("y", "x", -3, 3), true)
+            .unwrap();
+        propagated.sort();
+        assert_eq!(propagated, vec![(11, true), (12, false)]);
+    }
+
+    #[test]
+    fn test_negated_atoms() {
+        let mut theory = DifferenceLogic::new();
+        theory
+            .register_atom(Constraint::new("x", "y", 5, 10))
+            .unwrap();
+        theory
+            .register_atom(Constraint::new("y", "x", -3, 11))
+            .unwrap();
+        // not x - y <= 2 is x - y > 2, which makes atom 11 true and leaves atom 10 open.
+        let propagated = theory
+            .assert_literal(Constraint::new("x", "y", 2, 1), false)
+            .unwrap();
+        assert_eq!(propagated, vec![(11, true)]);
+        assert_eq!(theory.explain(&11), Some(&[(1, false)][..]));
+        assert_eq!(theory.value(&1), Some(false));
+        assert!(!theory.is_assigned(&10));
+
+        theory.new_level();
+        let Err(TheoryError::Conflict(mut conflict)) =
+            theory.assert_literal(Constraint::new("x", "y", 2, 2), true)
+        else {
+            panic!("expected a conflict");
+        };
+        conflict.sort();
+        assert_eq!(conflict, vec![(1, false), (2, true)]);
+        // not x - y < 3 is x - y >= 3, which fits.
+        theory
+            .assert_literal(Constraint::strict("x", "y", 3, 3), false)
+            .unwrap();
+
+        // overflow is reported, and leaves the theory as it was.
+        let mut theory = DifferenceLogic::new();
+        theory
+            .assert_literal(Constraint::new(1, 0, i64::MIN, 1), true)
+            .unwrap();
+        assert_eq!(
+            theory.assert_literal(Constraint::new(2, 1, -1, 2), true),
+            Err(TheoryError::Overflow)
+        );
+        assert_eq!(
+            theory.assert_literal(Constraint::new(2, 1, i64::MIN, 3), false),
+            Err(TheoryError::Overflow)
+        );
+        assert!(!theory.is_assigned(&2) && !theory.is_assigned(&3));
+        theory
+            .assert_literal(Constraint::new(2, 1, 5, 4), true)
+            .unwrap();
+        assert_eq!(theory.solution().get(&2), Some(&(i64::MIN + 5)));
+    }
+}